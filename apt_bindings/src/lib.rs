@@ -0,0 +1,152 @@
+use pazckager_core::models::{InstallationTools, PackageData, PackageMetadata, RawPackageData};
+pub use pazckager_core::permissions::PermissionMethod;
+use pazckager_core::program::{RunControl, find_program, listing_output, program_version};
+use pazckager_core::traits::{InstallationTool, ToolError, ToolResult};
+use std::collections::BTreeSet;
+use std::process::Command;
+
+const DPKG_QUERY_FORMAT: &str = "${Package}\t${db:Status-Abbrev}\n";
+
+pub struct AptInstaller {
+    permission_method: PermissionMethod,
+    control: RunControl,
+}
+
+impl AptInstaller {
+    pub fn new(permission_method: PermissionMethod) -> Self {
+        Self {
+            permission_method,
+            control: RunControl::default(),
+        }
+    }
+
+    fn apt_get_command(&self, args: &[&str], package_name: &str) -> Command {
+        let mut command = self.permission_method.command("apt-get");
+        command.args(args).arg("--yes").arg(package_name);
+        command
+    }
+}
+
+// Each line holds the package name and its status abbreviation, only the
+// packages marked for install and fully installed ("ii") are listed. Like
+// pacman -Qe, dependencies and libraries pulled in automatically are left out,
+// only the names apt-mark showmanual prints are kept
+fn parse_dpkg_query(output: &str, manual: &BTreeSet<&str>) -> Vec<RawPackageData> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (package_name, status) = line.split_once('\t')?;
+
+            if status.trim_end() != "ii" || !manual.contains(package_name) {
+                return None;
+            }
+
+            Some(RawPackageData {
+                package_name: package_name.to_string(),
+                category_name: String::new(),
+                installation_tool: InstallationTools::Apt,
+                metadata: PackageMetadata::new(),
+            })
+        })
+        .collect()
+}
+
+impl InstallationTool for AptInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Apt
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        find_program("apt-get").is_some()
+    }
+
+    fn version(&self) -> Option<String> {
+        program_version("apt-get")
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.control.run(
+            &mut self.apt_get_command(&["install"], &package.package_name),
+            "apt-get",
+            &package.package_name,
+            "install",
+            ToolError::InstallingPackage,
+        )
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.control.run(
+            &mut self.apt_get_command(&["remove", "--auto-remove"], &package.package_name),
+            "apt-get",
+            &package.package_name,
+            "remove",
+            ToolError::DeletingPackage,
+        )
+    }
+
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.control.run(
+            &mut self.apt_get_command(&["install", "--only-upgrade"], &package.package_name),
+            "apt-get",
+            &package.package_name,
+            "update",
+            ToolError::UpdatingPackage,
+        )
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        let mut command = Command::new("dpkg-query");
+        command.args(["--show", &format!("--showformat={DPKG_QUERY_FORMAT}")]);
+        let installed = listing_output(command, &self.control)?;
+
+        let mut command = Command::new("apt-mark");
+        command.arg("showmanual");
+        let manual = listing_output(command, &self.control)?;
+
+        Ok(parse_dpkg_query(
+            &installed,
+            &manual.lines().map(str::trim).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::command_line;
+
+    const DPKG_QUERY: &str = include_str!("../fixtures/dpkg_query.txt");
+
+    #[test]
+    fn test_commands_use_permission_method() {
+        let installer = AptInstaller::new(PermissionMethod::Pkexec);
+
+        assert_eq!(installer.get_type(), InstallationTools::Apt);
+        assert_eq!(
+            command_line(&installer.apt_get_command(&["remove", "--auto-remove"], "ripgrep")),
+            vec![
+                "pkexec",
+                "apt-get",
+                "remove",
+                "--auto-remove",
+                "--yes",
+                "ripgrep"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_dpkg_query() {
+        let manual = BTreeSet::from(["apt", "bash", "ripgrep", "vim-tiny"]);
+        let package_names = parse_dpkg_query(DPKG_QUERY, &manual)
+            .into_iter()
+            .map(|package| package.package_name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(package_names, vec!["apt", "bash", "ripgrep"]);
+    }
+}
@@ -0,0 +1,415 @@
+use pazckager_core::models::{InstallationTools, PackageData, PackageMetadata, RawPackageData};
+use pazckager_core::program::{RunControl, find_program, program_version};
+use pazckager_core::traits::{InstallationTool, ToolCapability, ToolError, ToolResult};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Package metadata keys understood by the installer, they map to the
+// `cargo install` flags with the same name. Features are comma separated and
+// locked is enabled with "true".
+pub const VERSION_KEY: &str = "version";
+pub const LOCKED_KEY: &str = "locked";
+pub const FEATURES_KEY: &str = "features";
+pub const GIT_KEY: &str = "git";
+pub const BRANCH_KEY: &str = "branch";
+pub const TAG_KEY: &str = "tag";
+pub const REV_KEY: &str = "rev";
+
+const GIT_REFERENCE_KEYS: [&str; 3] = [BRANCH_KEY, TAG_KEY, REV_KEY];
+
+// Written by cargo 1.41 onwards, keyed by package id:
+// "ripgrep 14.1.0 (registry+https://github.com/rust-lang/crates.io-index)"
+#[derive(Deserialize)]
+struct Crates2Json {
+    installs: BTreeMap<String, Crates2Install>,
+}
+
+#[derive(Deserialize)]
+struct Crates2Install {
+    version_req: Option<String>,
+    #[serde(default)]
+    features: Vec<String>,
+}
+
+// Older listing, still kept up to date by cargo, with only the package ids
+#[derive(Deserialize)]
+struct CratesToml {
+    #[serde(default)]
+    v1: BTreeMap<String, Vec<String>>,
+}
+
+pub struct CargoInstaller {
+    program: PathBuf,
+    cargo_home: PathBuf,
+    control: RunControl,
+}
+
+impl CargoInstaller {
+    pub fn new() -> Self {
+        let cargo_home = std::env::var("CARGO_HOME").unwrap_or("~/.cargo".to_string());
+
+        Self {
+            program: PathBuf::from("cargo"),
+            cargo_home: PathBuf::from(shellexpand::tilde(&cargo_home).to_string()),
+            control: RunControl::default(),
+        }
+    }
+
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    pub fn with_cargo_home(mut self, cargo_home: impl Into<PathBuf>) -> Self {
+        self.cargo_home = cargo_home.into();
+        self
+    }
+
+    fn install_command(&self, package: &PackageData) -> Command {
+        let metadata = &package.metadata;
+        let mut command = Command::new(&self.program);
+        command.arg("install");
+
+        if let Some(version) = metadata.get(VERSION_KEY) {
+            command.args(["--version", version]);
+        }
+
+        if metadata
+            .get(LOCKED_KEY)
+            .is_some_and(|locked| locked == "true")
+        {
+            command.arg("--locked");
+        }
+
+        if let Some(features) = metadata.get(FEATURES_KEY) {
+            command.args(["--features", features]);
+        }
+
+        if let Some(git) = metadata.get(GIT_KEY) {
+            command.args(["--git", git]);
+
+            for key in GIT_REFERENCE_KEYS {
+                if let Some(reference) = metadata.get(key) {
+                    command.arg(format!("--{key}")).arg(reference);
+                }
+            }
+        }
+
+        command.arg(&package.package_name);
+        command
+    }
+
+    // A cargo home without any of the files has nothing installed yet, any
+    // other failure to read or parse them is a listing error
+    fn installed_crates(&self) -> ToolResult<BTreeMap<String, Crates2Install>> {
+        if let Some(content) = read_optional(&self.cargo_home.join(".crates2.json"))? {
+            return serde_json::from_str::<Crates2Json>(&content)
+                .map(|crates| crates.installs)
+                .map_err(|e| ToolError::ListingPackages(format!("Invalid .crates2.json: {e}")));
+        }
+
+        let Some(content) = read_optional(&self.cargo_home.join(".crates.toml"))? else {
+            return Ok(BTreeMap::new());
+        };
+        let crates = toml::from_str::<CratesToml>(&content)
+            .map_err(|e| ToolError::ListingPackages(format!("Invalid .crates.toml: {e}")))?;
+
+        Ok(crates
+            .v1
+            .into_keys()
+            .map(|package_id| {
+                (
+                    package_id,
+                    Crates2Install {
+                        version_req: None,
+                        features: Vec::new(),
+                    },
+                )
+            })
+            .collect())
+    }
+}
+
+fn read_optional(path: &Path) -> ToolResult<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(Some(content)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ToolError::ListingPackages(format!(
+            "Failed to read {}: {e}",
+            path.display()
+        ))),
+    }
+}
+
+impl Default for CargoInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Splits "name version (source)" into the name and the source
+fn parse_package_id(package_id: &str) -> Option<(&str, &str)> {
+    let mut parts = package_id.splitn(3, ' ');
+    let package_name = parts.next()?;
+    let _version = parts.next()?;
+    let source = parts.next()?.trim_start_matches('(').trim_end_matches(')');
+
+    Some((package_name, source))
+}
+
+// Git sources look like "git+https://github.com/owner/repo?branch=main#commit"
+fn git_source_metadata(source: &str, metadata: &mut PackageMetadata) {
+    let Some(url) = source.strip_prefix("git+") else {
+        return;
+    };
+
+    let url = url.split('#').next().unwrap_or(url);
+    let (url, query) = url.split_once('?').unwrap_or((url, ""));
+
+    metadata.insert(GIT_KEY.to_string(), url.to_string());
+
+    for (key, value) in query.split('&').filter_map(|pair| pair.split_once('=')) {
+        if GIT_REFERENCE_KEYS.contains(&key) {
+            metadata.insert(key.to_string(), value.to_string());
+        }
+    }
+}
+
+impl InstallationTool for CargoInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Cargo
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        find_program(&self.program).is_some()
+    }
+
+    fn version(&self) -> Option<String> {
+        program_version(&self.program)
+    }
+
+    fn capabilities(&self) -> BTreeSet<ToolCapability> {
+        BTreeSet::from([ToolCapability::Versions, ToolCapability::UserScope])
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.control.run(
+            &mut self.install_command(package),
+            "cargo",
+            &package.package_name,
+            "install",
+            ToolError::InstallingPackage,
+        )
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = Command::new(&self.program);
+        command.args(["uninstall", &package.package_name]);
+
+        self.control.run(
+            &mut command,
+            "cargo",
+            &package.package_name,
+            "remove",
+            ToolError::DeletingPackage,
+        )
+    }
+
+    // cargo install replaces the installed crate when a newer version matches
+    // the requirement, so updating uses the same options as installing
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.control.run(
+            &mut self.install_command(package),
+            "cargo",
+            &package.package_name,
+            "update",
+            ToolError::UpdatingPackage,
+        )
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        Ok(self
+            .installed_crates()?
+            .into_iter()
+            .filter_map(|(package_id, install)| {
+                let (package_name, source) = parse_package_id(&package_id)?;
+                let mut metadata = PackageMetadata::new();
+
+                if let Some(version_req) = install.version_req {
+                    metadata.insert(VERSION_KEY.to_string(), version_req);
+                }
+
+                if !install.features.is_empty() {
+                    metadata.insert(FEATURES_KEY.to_string(), install.features.join(","));
+                }
+
+                git_source_metadata(source, &mut metadata);
+
+                Some(RawPackageData {
+                    package_name: package_name.to_string(),
+                    category_name: String::new(),
+                    installation_tool: InstallationTools::Cargo,
+                    metadata,
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::{FAKE_TOOL, calls, fake_program, test_package};
+
+    const CRATES2_JSON: &str = r#"{
+        "installs": {
+            "ripgrep 14.1.0 (registry+https://github.com/rust-lang/crates.io-index)": {
+                "version_req": "^14",
+                "bins": ["rg"],
+                "features": ["pcre2"],
+                "all_features": false,
+                "no_default_features": false,
+                "profile": "release",
+                "target": "x86_64-unknown-linux-gnu",
+                "rustc": "rustc 1.85.0"
+            },
+            "jj-cli 0.20.0 (git+https://github.com/martinvonz/jj?tag=v0.20.0#a1b2c3d)": {
+                "version_req": null,
+                "bins": ["jj"],
+                "features": [],
+                "all_features": false,
+                "no_default_features": false,
+                "profile": "release",
+                "target": "x86_64-unknown-linux-gnu",
+                "rustc": "rustc 1.85.0"
+            }
+        }
+    }"#;
+
+    const CRATES_TOML: &str = r#"[v1]
+"bat 0.24.0 (registry+https://github.com/rust-lang/crates.io-index)" = ["bat"]
+"#;
+
+    #[test]
+    fn test_install_options() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "cargo", FAKE_TOOL);
+        let mut installer = CargoInstaller::new().with_program(&program);
+
+        installer.install_package(&test_package(InstallationTools::Cargo, "bat", &[]))?;
+        installer.install_package(&test_package(
+            InstallationTools::Cargo,
+            "ripgrep",
+            &[
+                (VERSION_KEY, "^14"),
+                (LOCKED_KEY, "true"),
+                (FEATURES_KEY, "pcre2,simd-accel"),
+            ],
+        ))?;
+        installer.update_package(&test_package(
+            InstallationTools::Cargo,
+            "jj-cli",
+            &[
+                (GIT_KEY, "https://github.com/martinvonz/jj"),
+                (TAG_KEY, "v0.20.0"),
+                (LOCKED_KEY, "false"),
+            ],
+        ))?;
+        installer.delete_package(&test_package(InstallationTools::Cargo, "bat", &[]))?;
+
+        assert_eq!(
+            calls(&program),
+            vec![
+                "install bat",
+                "install --version ^14 --locked --features pcre2,simd-accel ripgrep",
+                "install --git https://github.com/martinvonz/jj --tag v0.20.0 jj-cli",
+                "uninstall bat",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_packages_from_crates2_json() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join(".crates2.json"), CRATES2_JSON).unwrap();
+        fs::write(temp_dir.path().join(".crates.toml"), CRATES_TOML).unwrap();
+        let installer = CargoInstaller::new().with_cargo_home(temp_dir.path());
+
+        let packages = installer
+            .get_packages()
+            .unwrap()
+            .into_iter()
+            .map(|package| (package.package_name, package.metadata))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            packages,
+            vec![
+                (
+                    "jj-cli".to_string(),
+                    test_package(
+                        InstallationTools::Cargo,
+                        "jj-cli",
+                        &[
+                            (GIT_KEY, "https://github.com/martinvonz/jj"),
+                            (TAG_KEY, "v0.20.0")
+                        ]
+                    )
+                    .metadata
+                ),
+                (
+                    "ripgrep".to_string(),
+                    test_package(
+                        InstallationTools::Cargo,
+                        "ripgrep",
+                        &[(VERSION_KEY, "^14"), (FEATURES_KEY, "pcre2")]
+                    )
+                    .metadata
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_get_packages_from_crates_toml() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join(".crates.toml"), CRATES_TOML).unwrap();
+        let installer = CargoInstaller::new().with_cargo_home(temp_dir.path());
+
+        let packages = installer.get_packages().unwrap();
+
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].package_name, "bat");
+        assert!(packages[0].metadata.is_empty());
+    }
+
+    #[test]
+    fn test_get_packages_without_installs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let installer = CargoInstaller::new().with_cargo_home(temp_dir.path());
+
+        assert!(installer.get_packages().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_get_packages_with_invalid_crates_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs::write(temp_dir.path().join(".crates2.json"), "{").unwrap();
+        let installer = CargoInstaller::new().with_cargo_home(temp_dir.path());
+
+        assert!(matches!(
+            installer.get_packages(),
+            Err(ToolError::ListingPackages(_))
+        ));
+    }
+}
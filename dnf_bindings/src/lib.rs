@@ -0,0 +1,144 @@
+use pazckager_core::models::{InstallationTools, PackageData, PackageMetadata, RawPackageData};
+pub use pazckager_core::permissions::PermissionMethod;
+use pazckager_core::program::{RunControl, find_program, listing_output, program_version};
+use pazckager_core::traits::{InstallationTool, ToolError, ToolResult};
+use std::process::Command;
+
+// Imported gpg keys show up in the rpm database as packages with this name
+const GPG_PUBKEY_PACKAGE: &str = "gpg-pubkey";
+
+pub struct DnfInstaller {
+    permission_method: PermissionMethod,
+    control: RunControl,
+}
+
+impl DnfInstaller {
+    pub fn new(permission_method: PermissionMethod) -> Self {
+        Self {
+            permission_method,
+            control: RunControl::default(),
+        }
+    }
+
+    fn dnf_command(&self, subcommand: &str, package_name: &str) -> Command {
+        let mut command = self.permission_method.command("dnf");
+        command.args([subcommand, "--assumeyes", package_name]);
+        command
+    }
+}
+
+// One package name per line, as printed by `rpm -qa --qf '%{NAME}\n'`. Several
+// versions of the same package (like kernels) show up once.
+fn parse_rpm_query(output: &str) -> Vec<RawPackageData> {
+    let mut package_names: Vec<&str> = output
+        .lines()
+        .map(str::trim)
+        .filter(|package_name| !package_name.is_empty() && *package_name != GPG_PUBKEY_PACKAGE)
+        .collect();
+
+    package_names.sort();
+    package_names.dedup();
+
+    package_names
+        .into_iter()
+        .map(|package_name| RawPackageData {
+            package_name: package_name.to_string(),
+            category_name: String::new(),
+            installation_tool: InstallationTools::Dnf,
+            metadata: PackageMetadata::new(),
+        })
+        .collect()
+}
+
+impl InstallationTool for DnfInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Dnf
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        find_program("dnf").is_some()
+    }
+
+    fn version(&self) -> Option<String> {
+        program_version("dnf")
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.control.run(
+            &mut self.dnf_command("install", &package.package_name),
+            "dnf",
+            &package.package_name,
+            "install",
+            ToolError::InstallingPackage,
+        )
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.control.run(
+            &mut self.dnf_command("remove", &package.package_name),
+            "dnf",
+            &package.package_name,
+            "remove",
+            ToolError::DeletingPackage,
+        )
+    }
+
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.control.run(
+            &mut self.dnf_command("upgrade", &package.package_name),
+            "dnf",
+            &package.package_name,
+            "update",
+            ToolError::UpdatingPackage,
+        )
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        let mut command = Command::new("rpm");
+        command.args(["-qa", "--qf", "%{NAME}\\n"]);
+
+        Ok(parse_rpm_query(&listing_output(command, &self.control)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::command_line;
+
+    const RPM_QA: &str = include_str!("../fixtures/rpm_qa.txt");
+
+    #[test]
+    fn test_commands_use_permission_method() {
+        let installer = DnfInstaller::new(PermissionMethod::Sudo);
+
+        assert_eq!(installer.get_type(), InstallationTools::Dnf);
+        assert_eq!(
+            command_line(&installer.dnf_command("upgrade", "ripgrep")),
+            vec!["sudo", "dnf", "upgrade", "--assumeyes", "ripgrep"]
+        );
+    }
+
+    #[test]
+    fn test_parse_rpm_query() {
+        let package_names = parse_rpm_query(RPM_QA)
+            .into_iter()
+            .map(|package| package.package_name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            package_names,
+            vec![
+                "bash",
+                "coreutils",
+                "kernel-core",
+                "ripgrep",
+                "vim-enhanced"
+            ]
+        );
+    }
+}
@@ -0,0 +1,267 @@
+use pazckager_core::models::{InstallationTools, PackageData, PackageMetadata, RawPackageData};
+use pazckager_core::program::{RunControl, find_program, listing_output, program_version};
+use pazckager_core::traits::{InstallationTool, ToolCapability, ToolError, ToolResult};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::process::Command;
+
+// Package metadata key holding the remote an app is installed from, when it
+// is missing flatpak looks the app up in every configured remote
+pub const REMOTE_KEY: &str = "remote";
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum FlatpakInstallation {
+    #[default]
+    User,
+    System,
+}
+
+impl FlatpakInstallation {
+    fn flag(&self) -> &'static str {
+        match self {
+            FlatpakInstallation::User => "--user",
+            FlatpakInstallation::System => "--system",
+        }
+    }
+}
+
+pub struct FlatpakInstaller {
+    installation: FlatpakInstallation,
+    program: PathBuf,
+    control: RunControl,
+}
+
+impl FlatpakInstaller {
+    pub fn new(installation: FlatpakInstallation) -> Self {
+        Self {
+            installation,
+            program: PathBuf::from("flatpak"),
+            control: RunControl::default(),
+        }
+    }
+
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    fn create_command(&self, subcommand: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args([subcommand, self.installation.flag()]);
+        command
+    }
+}
+
+impl InstallationTool for FlatpakInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Flatpak
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        find_program(&self.program).is_some()
+    }
+
+    fn version(&self) -> Option<String> {
+        program_version(&self.program)
+    }
+
+    fn capabilities(&self) -> BTreeSet<ToolCapability> {
+        if self.installation == FlatpakInstallation::User {
+            BTreeSet::from([ToolCapability::UserScope])
+        } else {
+            BTreeSet::new()
+        }
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = self.create_command("install");
+        command.arg("--noninteractive");
+
+        if let Some(remote) = package.metadata.get(REMOTE_KEY) {
+            command.arg(remote);
+        }
+
+        command.arg(&package.package_name);
+
+        self.control.run(
+            &mut command,
+            "flatpak",
+            &package.package_name,
+            "install",
+            ToolError::InstallingPackage,
+        )
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = self.create_command("uninstall");
+        command.args(["--noninteractive", &package.package_name]);
+
+        self.control.run(
+            &mut command,
+            "flatpak",
+            &package.package_name,
+            "remove",
+            ToolError::DeletingPackage,
+        )
+    }
+
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = self.create_command("update");
+        command.args(["--noninteractive", &package.package_name]);
+
+        self.control.run(
+            &mut command,
+            "flatpak",
+            &package.package_name,
+            "update",
+            ToolError::UpdatingPackage,
+        )
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        let mut command = self.create_command("list");
+        command.args(["--app", "--columns=application,origin"]);
+
+        Ok(listing_output(command, &self.control)?
+            .lines()
+            .filter_map(|line| {
+                let mut columns = line.split('\t');
+                let package_name = columns.next()?.trim();
+
+                if package_name.is_empty() {
+                    return None;
+                }
+
+                let metadata = columns
+                    .next()
+                    .map(str::trim)
+                    .filter(|remote| !remote.is_empty())
+                    .map(|remote| (REMOTE_KEY.to_string(), remote.to_string()))
+                    .into_iter()
+                    .collect::<PackageMetadata>();
+
+                Some(RawPackageData {
+                    package_name: package_name.to_string(),
+                    category_name: String::new(),
+                    installation_tool: InstallationTools::Flatpak,
+                    metadata,
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::{calls, check_capabilities, fake_program, test_package};
+
+    // Records its arguments next to itself and prints a fixed app list
+    const FAKE_FLATPAK: &str = "#!/bin/sh
+echo \"$@\" >> \"$0.calls\"
+case \"$*\" in
+    list*) printf 'org.mozilla.firefox\\tflathub\\norg.gnome.Builder\\tgnome-nightly\\n' ;;
+esac
+";
+
+    #[test]
+    fn test_install_uses_remote_and_installation() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "flatpak", FAKE_FLATPAK);
+        let mut installer =
+            FlatpakInstaller::new(FlatpakInstallation::System).with_program(&program);
+
+        installer.install_package(&test_package(
+            InstallationTools::Flatpak,
+            "org.mozilla.firefox",
+            &[(REMOTE_KEY, "flathub")],
+        ))?;
+        installer.install_package(&test_package(
+            InstallationTools::Flatpak,
+            "org.gnome.Builder",
+            &[],
+        ))?;
+
+        assert_eq!(
+            calls(&program),
+            vec![
+                "install --system --noninteractive flathub org.mozilla.firefox",
+                "install --system --noninteractive org.gnome.Builder",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uninstall_and_update() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "flatpak", FAKE_FLATPAK);
+        let mut installer = FlatpakInstaller::new(FlatpakInstallation::User).with_program(&program);
+        let package = test_package(InstallationTools::Flatpak, "org.mozilla.firefox", &[]);
+
+        installer.update_package(&package)?;
+        installer.delete_package(&package)?;
+
+        assert_eq!(
+            calls(&program),
+            vec![
+                "update --user --noninteractive org.mozilla.firefox",
+                "uninstall --user --noninteractive org.mozilla.firefox",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_availability_and_capabilities() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "flatpak", FAKE_FLATPAK);
+        let user = FlatpakInstaller::new(FlatpakInstallation::User).with_program(&program);
+        let system = FlatpakInstaller::new(FlatpakInstallation::System)
+            .with_program(temp_dir.path().join("missing"));
+
+        assert!(user.is_available());
+        assert_eq!(
+            user.capabilities(),
+            BTreeSet::from([ToolCapability::UserScope])
+        );
+        assert!(!system.is_available());
+        assert!(system.capabilities().is_empty());
+        check_capabilities(
+            &user,
+            &test_package(InstallationTools::Flatpak, "org.mozilla.firefox", &[]),
+        );
+    }
+
+    #[test]
+    fn test_get_packages() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "flatpak", FAKE_FLATPAK);
+        let installer = FlatpakInstaller::new(FlatpakInstallation::User).with_program(&program);
+
+        let packages = installer.get_packages().unwrap();
+
+        assert_eq!(
+            calls(&program),
+            vec!["list --user --app --columns=application,origin"]
+        );
+        assert_eq!(
+            packages
+                .into_iter()
+                .map(|package| (package.package_name, package.metadata[REMOTE_KEY].clone()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("org.mozilla.firefox".to_string(), "flathub".to_string()),
+                ("org.gnome.Builder".to_string(), "gnome-nightly".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,394 @@
+use pazckager_core::models::{InstallationTools, PackageData, PackageMetadata, RawPackageData};
+use pazckager_core::program::{RunControl, find_program, listing_output, program_version};
+use pazckager_core::traits::{InstallationTool, ToolCapability, ToolError, ToolResult};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::process::Command;
+
+// Package metadata key holding the flake the package comes from, like
+// "nixpkgs" or "github:helix-editor/helix"
+pub const FLAKE_KEY: &str = "flake";
+// Package metadata key pinning the flake to a revision, the package is then
+// kept at that revision on updates
+pub const REV_KEY: &str = "rev";
+
+const DEFAULT_FLAKE: &str = "nixpkgs";
+const INDIRECT_FLAKE_PREFIX: &str = "flake:";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileElement {
+    attr_path: Option<String>,
+    original_url: Option<String>,
+}
+
+// Since nix 2.20 the elements are keyed by their name, older versions list
+// them without a name
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ProfileElements {
+    Named(BTreeMap<String, ProfileElement>),
+    Unnamed(Vec<ProfileElement>),
+}
+
+#[derive(Deserialize)]
+struct ProfileList {
+    elements: ProfileElements,
+}
+
+pub struct NixInstaller {
+    program: PathBuf,
+    control: RunControl,
+}
+
+impl NixInstaller {
+    pub fn new() -> Self {
+        Self {
+            program: PathBuf::from("nix"),
+            control: RunControl::default(),
+        }
+    }
+
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    fn create_command(&self, subcommand: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(["profile", subcommand]);
+        command
+    }
+
+    fn install(
+        &self,
+        package: &PackageData,
+        action: &str,
+        to_error: fn(String) -> ToolError,
+    ) -> ToolResult<()> {
+        let mut command = self.create_command("install");
+        command.arg(installable(package));
+
+        self.control
+            .run(&mut command, "nix", &package.package_name, action, to_error)
+    }
+
+    fn remove(
+        &self,
+        package: &PackageData,
+        action: &str,
+        to_error: fn(String) -> ToolError,
+    ) -> ToolResult<()> {
+        let mut command = self.create_command("remove");
+        command.arg(&package.package_name);
+
+        self.control
+            .run(&mut command, "nix", &package.package_name, action, to_error)
+    }
+}
+
+impl Default for NixInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn installable(package: &PackageData) -> String {
+    let flake = package
+        .metadata
+        .get(FLAKE_KEY)
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_FLAKE);
+
+    match package.metadata.get(REV_KEY) {
+        Some(rev) if flake.contains('?') => format!("{flake}&rev={rev}#{}", package.package_name),
+        Some(rev) => format!("{flake}?rev={rev}#{}", package.package_name),
+        None => format!("{flake}#{}", package.package_name),
+    }
+}
+
+// Splits the flake reference the element was installed from into the flake
+// and the pinned revision, if any
+fn flake_metadata(original_url: &str) -> PackageMetadata {
+    let original_url = original_url
+        .strip_prefix(INDIRECT_FLAKE_PREFIX)
+        .unwrap_or(original_url);
+    let mut metadata = PackageMetadata::new();
+
+    let (flake, query) = match original_url.split_once('?') {
+        Some((flake, query)) => (flake, query),
+        None => (original_url, ""),
+    };
+
+    let mut other_params = Vec::new();
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        match param.strip_prefix("rev=") {
+            Some(rev) => {
+                metadata.insert(REV_KEY.to_string(), rev.to_string());
+            }
+            None => other_params.push(param),
+        }
+    }
+
+    let flake = if other_params.is_empty() {
+        flake.to_string()
+    } else {
+        format!("{flake}?{}", other_params.join("&"))
+    };
+    metadata.insert(FLAKE_KEY.to_string(), flake);
+
+    metadata
+}
+
+fn raw_package(package_name: String, element: &ProfileElement) -> RawPackageData {
+    RawPackageData {
+        package_name,
+        category_name: String::new(),
+        installation_tool: InstallationTools::Nix,
+        metadata: element
+            .original_url
+            .as_deref()
+            .map(flake_metadata)
+            .unwrap_or_default(),
+    }
+}
+
+// Unnamed elements are named after the last part of their attribute path,
+// like nix itself does
+fn parse_profile_list(output: &str) -> ToolResult<Vec<RawPackageData>> {
+    let profile_list = serde_json::from_str::<ProfileList>(output)
+        .map_err(|e| ToolError::ListingPackages(format!("Invalid nix profile list output: {e}")))?;
+
+    Ok(match profile_list.elements {
+        ProfileElements::Named(elements) => elements
+            .into_iter()
+            .map(|(package_name, element)| raw_package(package_name, &element))
+            .collect(),
+        ProfileElements::Unnamed(elements) => elements
+            .iter()
+            .filter_map(|element| {
+                let attr_path = element.attr_path.as_deref()?;
+                let package_name = attr_path.rsplit('.').next()?;
+                Some(raw_package(package_name.to_string(), element))
+            })
+            .collect(),
+    })
+}
+
+impl InstallationTool for NixInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Nix
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        find_program(&self.program).is_some()
+    }
+
+    fn version(&self) -> Option<String> {
+        program_version(&self.program)
+    }
+
+    fn capabilities(&self) -> BTreeSet<ToolCapability> {
+        BTreeSet::from([ToolCapability::Versions, ToolCapability::UserScope])
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.install(package, "install", ToolError::InstallingPackage)
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.remove(package, "remove", ToolError::DeletingPackage)
+    }
+
+    // nix profile upgrade keeps the locked revision of pinned packages, so
+    // they are only touched when the profile has another revision. The new
+    // one is built before the old one is removed, a bad revision or a network
+    // error leaves the installed package in place
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        if let Some(rev) = package.metadata.get(REV_KEY) {
+            let pinned = self.get_packages()?.into_iter().any(|installed| {
+                installed.package_name == package.package_name
+                    && installed.metadata.get(REV_KEY) == Some(rev)
+            });
+
+            if pinned {
+                return Ok(());
+            }
+
+            let mut command = Command::new(&self.program);
+            command.args(["build", "--no-link", &installable(package)]);
+            self.control.run(
+                &mut command,
+                "nix",
+                &package.package_name,
+                "update",
+                ToolError::UpdatingPackage,
+            )?;
+
+            self.remove(package, "update", ToolError::UpdatingPackage)?;
+            return self.install(package, "update", ToolError::UpdatingPackage);
+        }
+
+        let mut command = self.create_command("upgrade");
+        command.arg(&package.package_name);
+
+        self.control.run(
+            &mut command,
+            "nix",
+            &package.package_name,
+            "update",
+            ToolError::UpdatingPackage,
+        )
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        let mut command = self.create_command("list");
+        command.arg("--json");
+
+        parse_profile_list(&listing_output(command, &self.control)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::{FAKE_TOOL, calls, fake_program, test_package};
+    use std::path::Path;
+
+    const NIX_PROFILE_LIST: &str = include_str!("../fixtures/nix_profile_list.json");
+    const NIX_PROFILE_LIST_V2: &str = include_str!("../fixtures/nix_profile_list_v2.json");
+
+    fn pinned_hello(rev: &str) -> PackageData {
+        test_package(
+            InstallationTools::Nix,
+            "hello",
+            &[(FLAKE_KEY, "github:NixOS/nixpkgs"), (REV_KEY, rev)],
+        )
+    }
+
+    #[test]
+    fn test_commands() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "nix", FAKE_TOOL);
+        let mut installer = NixInstaller::new().with_program(&program);
+
+        installer.install_package(&test_package(InstallationTools::Nix, "ripgrep", &[]))?;
+        installer.install_package(&pinned_hello("5e4fbfb"))?;
+        installer.update_package(&test_package(InstallationTools::Nix, "ripgrep", &[]))?;
+        installer.delete_package(&test_package(InstallationTools::Nix, "ripgrep", &[]))?;
+
+        assert_eq!(
+            calls(&program),
+            vec![
+                "profile install nixpkgs#ripgrep",
+                "profile install github:NixOS/nixpkgs?rev=5e4fbfb#hello",
+                "profile upgrade ripgrep",
+                "profile remove ripgrep",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pinned_update() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(
+            temp_dir.path(),
+            "nix",
+            &format!(
+                "#!/bin/sh\necho \"$@\" >> \"$0.calls\"\ncase \"$*\" in\n    *broken*) exit 1 ;;\n    \"profile list --json\") cat {} ;;\nesac\n",
+                Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("fixtures/nix_profile_list.json")
+                    .display()
+            ),
+        );
+        let mut installer = NixInstaller::new().with_program(&program);
+
+        // The profile already has this revision
+        installer.update_package(&pinned_hello("5e4fbfb6b3de1aa2872b76d49fafc942626e2add"))?;
+        installer.update_package(&pinned_hello("b06025f"))?;
+        assert!(matches!(
+            installer.update_package(&pinned_hello("broken")),
+            Err(ToolError::UpdatingPackage(_))
+        ));
+
+        assert_eq!(
+            calls(&program),
+            vec![
+                "profile list --json",
+                "profile list --json",
+                "build --no-link github:NixOS/nixpkgs?rev=b06025f#hello",
+                "profile remove hello",
+                "profile install github:NixOS/nixpkgs?rev=b06025f#hello",
+                "profile list --json",
+                "build --no-link github:NixOS/nixpkgs?rev=broken#hello",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_installable_keeps_flake_query() {
+        let package = test_package(
+            InstallationTools::Nix,
+            "hello",
+            &[
+                (FLAKE_KEY, "git+https://example.com/flake?ref=main"),
+                (REV_KEY, "abc"),
+            ],
+        );
+
+        assert_eq!(
+            installable(&package),
+            "git+https://example.com/flake?ref=main&rev=abc#hello"
+        );
+    }
+
+    #[test]
+    fn test_parse_profile_list() {
+        let packages = parse_profile_list(NIX_PROFILE_LIST)
+            .unwrap()
+            .into_iter()
+            .map(|package| (package.package_name, package.metadata))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            packages,
+            vec![
+                (
+                    "hello".to_string(),
+                    PackageMetadata::from([
+                        (FLAKE_KEY.to_string(), "github:NixOS/nixpkgs".to_string()),
+                        (
+                            REV_KEY.to_string(),
+                            "5e4fbfb6b3de1aa2872b76d49fafc942626e2add".to_string()
+                        ),
+                    ])
+                ),
+                (
+                    "ripgrep".to_string(),
+                    PackageMetadata::from([(FLAKE_KEY.to_string(), "nixpkgs".to_string())])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_unnamed_profile_list() {
+        let packages = parse_profile_list(NIX_PROFILE_LIST_V2).unwrap();
+
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].package_name, "default");
+        assert_eq!(
+            packages[0].metadata.get(FLAKE_KEY).map(String::as_str),
+            Some("github:helix-editor/helix")
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use pazckager_core::models::{InstallationTools, PackageData, PackageMetadata, RawPackageData};
+use pazckager_core::program::{RunControl, find_program, listing_output, program_version};
+use pazckager_core::traits::{InstallationTool, ToolCapability, ToolError, ToolResult};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::process::Command;
+
+// Package metadata key with the version or dist-tag npm installs, like "17"
+// or "next". npm ls can't tell which one was asked for, so synced packages
+// have none
+pub const VERSION_KEY: &str = "version";
+
+#[derive(Deserialize)]
+struct NpmList {
+    #[serde(default)]
+    dependencies: BTreeMap<String, serde_json::Value>,
+}
+
+pub struct NpmInstaller {
+    program: PathBuf,
+    prefix: Option<PathBuf>,
+    control: RunControl,
+}
+
+impl NpmInstaller {
+    pub fn new() -> Self {
+        Self {
+            program: PathBuf::from("npm"),
+            prefix: None,
+            control: RunControl::default(),
+        }
+    }
+
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    // Per user global prefix, like ~/.npm-global, so global installs don't
+    // need root
+    pub fn with_prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    fn create_command(&self, subcommand: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args([subcommand, "--global"]);
+
+        if let Some(prefix) = &self.prefix {
+            command.arg("--prefix").arg(prefix);
+        }
+
+        command
+    }
+}
+
+impl Default for NpmInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn package_spec(package: &PackageData) -> String {
+    package.versioned_name(VERSION_KEY, "@")
+}
+
+// The npm bundled with node shows up as a global package, it isn't tracked so
+// removing a category can't remove it
+fn parse_npm_list(output: &str) -> ToolResult<Vec<RawPackageData>> {
+    let npm_list = serde_json::from_str::<NpmList>(output)
+        .map_err(|e| ToolError::ListingPackages(format!("Invalid npm ls output: {e}")))?;
+
+    Ok(npm_list
+        .dependencies
+        .into_keys()
+        .filter(|package_name| package_name != "npm")
+        .map(|package_name| RawPackageData {
+            package_name,
+            category_name: String::new(),
+            installation_tool: InstallationTools::Npm,
+            metadata: PackageMetadata::new(),
+        })
+        .collect())
+}
+
+impl InstallationTool for NpmInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Npm
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        find_program(&self.program).is_some()
+    }
+
+    fn version(&self) -> Option<String> {
+        program_version(&self.program)
+    }
+
+    fn capabilities(&self) -> BTreeSet<ToolCapability> {
+        let mut capabilities = BTreeSet::from([ToolCapability::Versions]);
+
+        // Global installs only stay in the user home with a custom prefix
+        if self.prefix.is_some() {
+            capabilities.insert(ToolCapability::UserScope);
+        }
+
+        capabilities
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = self.create_command("install");
+        command.arg(package_spec(package));
+
+        self.control.run(
+            &mut command,
+            "npm",
+            &package.package_name,
+            "install",
+            ToolError::InstallingPackage,
+        )
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = self.create_command("uninstall");
+        command.arg(&package.package_name);
+
+        self.control.run(
+            &mut command,
+            "npm",
+            &package.package_name,
+            "remove",
+            ToolError::DeletingPackage,
+        )
+    }
+
+    // npm update would move pinned packages to the latest version, so they are
+    // installed again at their version instead
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = if package.metadata.contains_key(VERSION_KEY) {
+            let mut command = self.create_command("install");
+            command.arg(package_spec(package));
+            command
+        } else {
+            let mut command = self.create_command("update");
+            command.arg(&package.package_name);
+            command
+        };
+
+        self.control.run(
+            &mut command,
+            "npm",
+            &package.package_name,
+            "update",
+            ToolError::UpdatingPackage,
+        )
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        let mut command = self.create_command("ls");
+        command.args(["--json", "--depth=0"]);
+
+        parse_npm_list(&listing_output(command, &self.control)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::{
+        FAKE_TOOL, calls, check_capabilities, fake_program, test_package,
+    };
+
+    const NPM_LS: &str = include_str!("../fixtures/npm_ls.json");
+
+    #[test]
+    fn test_commands() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "npm", FAKE_TOOL);
+        let mut installer = NpmInstaller::new()
+            .with_program(&program)
+            .with_prefix("/home/user/.npm-global");
+
+        installer.install_package(&test_package(
+            InstallationTools::Npm,
+            "@angular/cli",
+            &[(VERSION_KEY, "17")],
+        ))?;
+        installer.update_package(&test_package(
+            InstallationTools::Npm,
+            "@angular/cli",
+            &[(VERSION_KEY, "17")],
+        ))?;
+        installer.update_package(&test_package(InstallationTools::Npm, "typescript", &[]))?;
+        installer.delete_package(&test_package(InstallationTools::Npm, "typescript", &[]))?;
+
+        assert_eq!(
+            calls(&program),
+            vec![
+                "install --global --prefix /home/user/.npm-global @angular/cli@17",
+                "install --global --prefix /home/user/.npm-global @angular/cli@17",
+                "update --global --prefix /home/user/.npm-global typescript",
+                "uninstall --global --prefix /home/user/.npm-global typescript",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let installer = NpmInstaller::new().with_prefix("/home/user/.npm-global");
+
+        assert_eq!(
+            NpmInstaller::new().capabilities(),
+            BTreeSet::from([ToolCapability::Versions])
+        );
+        assert_eq!(
+            installer.capabilities(),
+            BTreeSet::from([ToolCapability::Versions, ToolCapability::UserScope])
+        );
+        check_capabilities(
+            &installer,
+            &test_package(InstallationTools::Npm, "typescript", &[]),
+        );
+    }
+
+    #[test]
+    fn test_parse_npm_list() {
+        let package_names = parse_npm_list(NPM_LS)
+            .unwrap()
+            .into_iter()
+            .map(|package| package.package_name)
+            .collect::<Vec<_>>();
+
+        assert_eq!(package_names, vec!["@angular/cli", "typescript"]);
+    }
+
+    #[test]
+    fn test_parse_invalid_npm_list() {
+        assert!(matches!(
+            parse_npm_list("npm: command not found"),
+            Err(ToolError::ListingPackages(_))
+        ));
+    }
+}
@@ -0,0 +1,51 @@
+use std::env;
+use std::io;
+use std::path::Path;
+use std::process::ExitCode;
+
+use pacman_bindings::PacmanOptions;
+use pacman_bindings::helper::{HELPER_NAME, PACMAN_PATH, polkit_policy, serve};
+
+// Runs as root, started by PacmanInstaller for a batch of packages:
+//
+// pazckager-pacman-helper [--root <path>] [--dbpath <path>] [--cachedir <path>]
+//                         [--needed] [--removal package|unneeded|full]
+// pazckager-pacman-helper --polkit-policy
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let options = match args.as_slice() {
+        [flag] if flag == "--polkit-policy" => {
+            return match env::current_exe() {
+                Ok(helper_path) => {
+                    print!("{}", polkit_policy(&helper_path));
+                    ExitCode::SUCCESS
+                }
+                Err(err) => {
+                    eprintln!("Could not find the helper path: {err}");
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        args => match PacmanOptions::from_helper_args(args) {
+            Ok(options) => options,
+            Err(err) => {
+                eprintln!("{HELPER_NAME}: {err}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    match serve(
+        io::stdin().lock(),
+        io::stdout().lock(),
+        Path::new(PACMAN_PATH),
+        &options,
+    ) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("{HELPER_NAME}: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,112 @@
+// File ownership from the pacman local database. The `files` file of every
+// package lists what it installed relative to the root, directories end with
+// a slash:
+//
+// %FILES%
+// usr/
+// usr/bin/
+// usr/bin/rg
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::graph::LocalPackage;
+
+fn parse_files(files: &str) -> impl Iterator<Item = &str> {
+    files
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| *line != "%FILES%")
+        .skip(1)
+        .take_while(|line| !line.starts_with('%'))
+        .filter(|line| !line.is_empty())
+        .map(|line| line.trim_end_matches('/'))
+}
+
+// The path as pacman records it. Paths inside the root are taken relative to
+// it, and the parent directories are resolved so /bin/ls finds usr/bin/ls on
+// systems where /bin links to /usr/bin
+fn relative_path(root: &Path, path: &Path) -> PathBuf {
+    let path = match (path.parent(), path.file_name()) {
+        (Some(parent), Some(file_name)) => fs::canonicalize(parent)
+            .map(|parent| parent.join(file_name))
+            .unwrap_or(path.to_path_buf()),
+        _ => path.to_path_buf(),
+    };
+
+    let root = fs::canonicalize(root).unwrap_or(root.to_path_buf());
+    path.strip_prefix(&root)
+        .or_else(|_| path.strip_prefix("/"))
+        .map(Path::to_path_buf)
+        .unwrap_or(path)
+}
+
+// Names of the installed packages owning the path, directories can be shared
+// by several packages
+pub fn file_owners(local_db: &Path, root: &Path, path: &Path) -> io::Result<Vec<String>> {
+    let relative = relative_path(root, path);
+    let Some(relative) = relative.to_str() else {
+        return Ok(Vec::new());
+    };
+
+    let mut owners = Vec::new();
+    for entry in fs::read_dir(local_db)? {
+        let package_dir = entry?.path();
+        let files = package_dir.join("files");
+
+        if !files.is_file()
+            || !parse_files(&fs::read_to_string(files)?).any(|file| file == relative)
+        {
+            continue;
+        }
+
+        let desc = fs::read_to_string(package_dir.join("desc"))?;
+        owners.extend(LocalPackage::parse_desc(&desc).map(|package| package.name));
+    }
+
+    owners.sort();
+    Ok(owners)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture_db() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/local")
+    }
+
+    #[test]
+    fn test_file_owners() -> io::Result<()> {
+        let root = Path::new("/");
+
+        assert_eq!(
+            file_owners(&fixture_db(), root, Path::new("/usr/bin/rg"))?,
+            vec!["ripgrep"]
+        );
+        assert_eq!(
+            file_owners(&fixture_db(), root, Path::new("/usr/share/doc/ripgrep"))?,
+            vec!["ripgrep"]
+        );
+        assert!(file_owners(&fixture_db(), root, Path::new("/usr/bin/fd"))?.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paths_inside_the_root() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("usr/bin")).unwrap();
+
+        assert_eq!(
+            relative_path(root, &root.join("usr/bin/rg")),
+            PathBuf::from("usr/bin/rg")
+        );
+        assert_eq!(
+            relative_path(root, Path::new("/usr/bin/rg")),
+            PathBuf::from("usr/bin/rg")
+        );
+    }
+}
@@ -0,0 +1,323 @@
+// Dependency graph of the packages in the pacman local database. Every
+// installed package has a directory there with a `desc` file made of
+// "%FIELD%" sections, one value per line:
+//
+// %NAME%
+// ripgrep
+//
+// %SIZE%
+// 4750336
+//
+// %DEPENDS%
+// gcc-libs
+// pcre2
+//
+// Dependencies can carry a version constraint and name a virtual package, they
+// are resolved to the installed package that provides them.
+
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LocalPackage {
+    pub name: String,
+    pub version: String,
+    // Installed size in bytes
+    pub size: u64,
+    pub depends: Vec<String>,
+    pub provides: Vec<String>,
+}
+
+impl LocalPackage {
+    pub fn parse_desc(desc: &str) -> Option<Self> {
+        let mut package = LocalPackage::default();
+        let mut field = "";
+
+        for line in desc.lines().map(str::trim) {
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.len() > 2 && line.starts_with('%') && line.ends_with('%') {
+                field = line;
+                continue;
+            }
+
+            match field {
+                "%NAME%" => package.name = line.to_string(),
+                "%VERSION%" => package.version = line.to_string(),
+                "%SIZE%" => package.size = line.parse().ok()?,
+                "%DEPENDS%" => package.depends.push(line.to_string()),
+                "%PROVIDES%" => package.provides.push(line.to_string()),
+                _ => {}
+            }
+        }
+
+        (!package.name.is_empty()).then_some(package)
+    }
+}
+
+// "libfoo.so=1-64" and "glibc>=2.38" name libfoo.so and glibc
+fn dependency_name(dependency: &str) -> &str {
+    dependency
+        .split(['<', '>', '='])
+        .next()
+        .unwrap_or(dependency)
+        .trim()
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    packages: BTreeMap<String, LocalPackage>,
+    // Provided name to the packages providing it
+    providers: BTreeMap<String, Vec<String>>,
+}
+
+impl DependencyGraph {
+    pub fn new(packages: impl IntoIterator<Item = LocalPackage>) -> Self {
+        let mut graph = Self::default();
+
+        for package in packages {
+            for provided in &package.provides {
+                graph
+                    .providers
+                    .entry(dependency_name(provided).to_string())
+                    .or_default()
+                    .push(package.name.clone());
+            }
+            graph.packages.insert(package.name.clone(), package);
+        }
+
+        graph
+    }
+
+    // Reads the `local` directory of a pacman database path
+    pub fn read(local_db: &Path) -> io::Result<Self> {
+        let mut packages = Vec::new();
+
+        for entry in fs::read_dir(local_db)? {
+            let desc = entry?.path().join("desc");
+
+            if desc.is_file()
+                && let Some(package) = LocalPackage::parse_desc(&fs::read_to_string(desc)?)
+            {
+                packages.push(package);
+            }
+        }
+
+        Ok(Self::new(packages))
+    }
+
+    pub fn package(&self, name: &str) -> Option<&LocalPackage> {
+        self.packages.get(name)
+    }
+
+    pub fn packages(&self) -> impl Iterator<Item = &LocalPackage> {
+        self.packages.values()
+    }
+
+    // The installed package satisfying a dependency, a package with the name
+    // itself wins over the providers
+    pub fn resolve(&self, dependency: &str) -> Option<&str> {
+        let name = dependency_name(dependency);
+
+        if let Some(package) = self.packages.get(name) {
+            return Some(&package.name);
+        }
+
+        self.providers
+            .get(name)
+            .and_then(|providers| providers.first())
+            .map(String::as_str)
+    }
+
+    // Installed packages the package depends on directly, dependencies that
+    // aren't installed are left out
+    pub fn dependencies(&self, name: &str) -> BTreeSet<&str> {
+        self.packages
+            .get(name)
+            .map(|package| {
+                package
+                    .depends
+                    .iter()
+                    .filter_map(|dependency| self.resolve(dependency))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    // Installed packages depending on each package
+    pub fn reverse_dependencies(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        let mut reverse: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+
+        for package in self.packages.values() {
+            for dependency in self.dependencies(&package.name) {
+                reverse.entry(dependency).or_default().insert(&package.name);
+            }
+        }
+
+        reverse
+    }
+
+    // Why a package is installed: the shortest chains of packages requiring
+    // it, from the package to each tracked package that needs it. Chains
+    // contained in a longer one are left out, so "libfoo <- bar" isn't
+    // repeated next to "libfoo <- bar <- baz" when bar and baz are tracked
+    pub fn why(&self, name: &str, is_tracked: impl Fn(&str) -> bool) -> Vec<Vec<&str>> {
+        let Some(package) = self.packages.get(name) else {
+            return Vec::new();
+        };
+
+        let reverse = self.reverse_dependencies();
+        let mut parents: BTreeMap<&str, &str> = BTreeMap::new();
+        let mut pending = VecDeque::from([package.name.as_str()]);
+        let mut tracked = Vec::new();
+
+        while let Some(current) = pending.pop_front() {
+            for &requiring in reverse.get(current).into_iter().flatten() {
+                if requiring == package.name || parents.contains_key(requiring) {
+                    continue;
+                }
+
+                parents.insert(requiring, current);
+                pending.push_back(requiring);
+                if is_tracked(requiring) {
+                    tracked.push(requiring);
+                }
+            }
+        }
+
+        let mut chains: Vec<Vec<&str>> = tracked
+            .into_iter()
+            .map(|mut current| {
+                let mut chain = vec![current];
+                while let Some(&parent) = parents.get(current) {
+                    chain.push(parent);
+                    current = parent;
+                }
+                chain.reverse();
+                chain
+            })
+            .collect();
+
+        chains.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        let mut longest: Vec<Vec<&str>> = Vec::new();
+        for chain in chains {
+            if !longest.iter().any(|other| other.starts_with(&chain)) {
+                longest.push(chain);
+            }
+        }
+
+        longest.sort();
+        longest
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn desc(name: &str, size: u64, depends: &[&str], provides: &[&str]) -> String {
+        let mut desc = format!("%NAME%\n{name}\n\n%VERSION%\n1.0-1\n\n%SIZE%\n{size}\n\n");
+        if !depends.is_empty() {
+            desc.push_str(&format!("%DEPENDS%\n{}\n\n", depends.join("\n")));
+        }
+        if !provides.is_empty() {
+            desc.push_str(&format!("%PROVIDES%\n{}\n\n", provides.join("\n")));
+        }
+        desc
+    }
+
+    // Writes a local database with one directory per package
+    fn write_local_db(local_db: &Path, descs: &[String]) {
+        for desc in descs {
+            let package = LocalPackage::parse_desc(desc).unwrap();
+            let package_dir = local_db.join(format!("{}-{}", package.name, package.version));
+            fs::create_dir_all(&package_dir).unwrap();
+            fs::write(package_dir.join("desc"), desc).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_parse_desc() {
+        let desc = include_str!("../fixtures/local/ripgrep-14.1.1-1/desc");
+
+        assert_eq!(
+            LocalPackage::parse_desc(desc),
+            Some(LocalPackage {
+                name: "ripgrep".to_string(),
+                version: "14.1.1-1".to_string(),
+                size: 4750336,
+                depends: vec![
+                    "gcc-libs".to_string(),
+                    "glibc".to_string(),
+                    "pcre2".to_string()
+                ],
+                provides: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_dependencies_resolve_providers_and_versions() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        write_local_db(
+            temp_dir.path(),
+            &[
+                desc(
+                    "app",
+                    10,
+                    &["glibc>=2.38", "libjack.so=0-64", "missing"],
+                    &[],
+                ),
+                desc("glibc", 100, &[], &[]),
+                desc("pipewire-jack", 20, &[], &["jack", "libjack.so=0-64"]),
+            ],
+        );
+
+        let graph = DependencyGraph::read(temp_dir.path()).unwrap();
+
+        assert_eq!(
+            graph.dependencies("app"),
+            BTreeSet::from(["glibc", "pipewire-jack"])
+        );
+        assert_eq!(graph.resolve("jack"), Some("pipewire-jack"));
+        assert_eq!(
+            graph.package("glibc").map(|package| package.size),
+            Some(100)
+        );
+    }
+
+    #[test]
+    fn test_why_walks_back_to_tracked_packages() {
+        let graph = DependencyGraph::new(
+            [
+                desc("libfoo", 1, &[], &[]),
+                desc("bar", 1, &["libfoo"], &[]),
+                desc("baz", 1, &["bar"], &[]),
+                desc("helper", 1, &["libfoo"], &[]),
+                desc("qux", 1, &["helper"], &[]),
+                desc("orphan", 1, &[], &[]),
+            ]
+            .iter()
+            .filter_map(|desc| LocalPackage::parse_desc(desc)),
+        );
+        let tracked = ["bar", "baz", "qux"];
+
+        assert_eq!(
+            graph.why("libfoo", |name| tracked.contains(&name)),
+            vec![
+                vec!["libfoo", "bar", "baz"],
+                vec!["libfoo", "helper", "qux"]
+            ]
+        );
+        assert!(
+            graph
+                .why("orphan", |name| tracked.contains(&name))
+                .is_empty()
+        );
+        assert!(graph.why("missing", |_| true).is_empty());
+    }
+}
@@ -0,0 +1,371 @@
+// Privileged helper that runs pacman for a whole batch of packages, so the
+// user authenticates once instead of once per package. The installer starts
+// `pazckager-pacman-helper` through its permission method and talks to it over
+// a pipe, one command per line:
+//
+// < ready
+// > install ripgrep
+// < ok
+// > remove --cascade
+// < error Invalid package name --cascade
+//
+// Only these verbs are accepted, and package names can't be pacman options:
+//
+// install  pacman -S <package> --noconfirm
+// remove   pacman -R, -Rs or -Rns <package> --noconfirm, as set by --removal
+// update   pacman -Sy <package> --noconfirm
+//
+// The `PacmanOptions` of the installer are fixed for the whole session, they
+// are given to the helper as its arguments. Anyone allowed to start the helper
+// picks them, so it refuses a config file and overwrite globs, only takes
+// directories that only root can write to and always runs /usr/bin/pacman,
+// see `PacmanOptions::check_helper_options`. Pacman can't ask questions from
+// the helper, interactive installers run it directly. When its stderr is
+// captured for progress events pacman runs in the C locale. The helper exits
+// once its stdin is closed. With pkexec, the policy from `polkit_policy` has
+// to be installed in /usr/share/polkit-1/actions.
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::thread::JoinHandle;
+
+use pazckager_core::program::{OutputLines, RunControl, RunError};
+
+use crate::options::PacmanOptions;
+use crate::progress::{ProgressHandler, forward_progress};
+
+pub const HELPER_NAME: &str = "pazckager-pacman-helper";
+// The helper never looks pacman up in the PATH of its caller
+pub const PACMAN_PATH: &str = "/usr/bin/pacman";
+pub const POLKIT_ACTION_ID: &str = "org.pazckager.pacman-helper";
+
+const READY: &str = "ready";
+const OK: &str = "ok";
+const ERROR_PREFIX: &str = "error ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verb {
+    Install,
+    Remove,
+    Update,
+}
+
+impl Verb {
+    fn name(&self) -> &'static str {
+        match self {
+            Verb::Install => "install",
+            Verb::Remove => "remove",
+            Verb::Update => "update",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [Verb::Install, Verb::Remove, Verb::Update]
+            .into_iter()
+            .find(|verb| verb.name() == name)
+    }
+}
+
+// Same characters pacman allows in package names, without a leading dash so a
+// name can never be read as an option
+fn is_valid_package_name(package_name: &str) -> bool {
+    !package_name.is_empty()
+        && !package_name.starts_with(['-', '.'])
+        && package_name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "@._+-".contains(c))
+}
+
+fn parse_command(line: &str) -> Result<(Verb, &str), String> {
+    let (verb, package_name) = line
+        .split_once(' ')
+        .ok_or(format!("Invalid command {line}"))?;
+    let verb = Verb::parse(verb).ok_or(format!("Unknown command {verb}"))?;
+
+    if !is_valid_package_name(package_name) {
+        return Err(format!("Invalid package name {package_name}"));
+    }
+
+    Ok((verb, package_name))
+}
+
+// Privileged side of the session, pacman output goes to stderr so it never
+// mixes with the replies
+pub fn serve(
+    input: impl BufRead,
+    mut output: impl Write,
+    pacman: &Path,
+    options: &PacmanOptions,
+) -> io::Result<()> {
+    writeln!(output, "{READY}")?;
+    output.flush()?;
+
+    let parsed = !io::stderr().is_terminal();
+
+    for line in input.lines() {
+        let line = line?;
+
+        let reply = parse_command(line.trim()).and_then(|(verb, package_name)| {
+            let mut command = Command::new(pacman);
+            if parsed {
+                command.env("LC_ALL", "C");
+            }
+
+            let status = command
+                .args(options.global_args())
+                .args(options.operation_args(verb, package_name))
+                .stdin(Stdio::null())
+                .stdout(io::stderr())
+                .status()
+                .map_err(|e| format!("Failed to execute pacman: {e}"))?;
+
+            if status.success() {
+                Ok(())
+            } else {
+                Err(format!("exit code {}", status.code().unwrap_or(-1)))
+            }
+        });
+
+        match reply {
+            Ok(()) => writeln!(output, "{OK}")?,
+            Err(message) => writeln!(output, "{ERROR_PREFIX}{message}")?,
+        }
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+// Unprivileged side, the helper process lives until the session is dropped
+#[derive(Debug)]
+pub enum SessionError {
+    // The helper or pacman failed, the message says why
+    Failed(String),
+    // Cancelled or timed out waiting for a reply, the helper has been stopped
+    Stopped(RunError),
+}
+
+impl From<String> for SessionError {
+    fn from(message: String) -> Self {
+        SessionError::Failed(message)
+    }
+}
+
+pub struct HelperSession {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: OutputLines,
+    progress: Option<JoinHandle<()>>,
+}
+
+impl HelperSession {
+    // Authentication happens while starting the command, the session is only
+    // usable once the helper reports that it is ready. With a progress handler
+    // the pacman output on the helper stderr is parsed instead of shown
+    pub fn start(
+        mut command: Command,
+        progress: Option<ProgressHandler>,
+        control: &RunControl,
+    ) -> Result<Self, SessionError> {
+        command.stdin(Stdio::piped()).stdout(Stdio::piped());
+        if progress.is_some() {
+            command.stderr(Stdio::piped());
+        }
+
+        let mut child = command
+            .spawn()
+            .map_err(|e| format!("Failed to execute {HELPER_NAME}: {e}"))?;
+
+        let stdin = child.stdin.take();
+        let stdout = OutputLines::new(
+            child
+                .stdout
+                .take()
+                .ok_or("Missing helper stdout".to_string())?,
+        );
+        let progress = progress
+            .zip(child.stderr.take())
+            .map(|(handler, stderr)| forward_progress(stderr, handler));
+
+        let mut session = Self {
+            child,
+            stdin,
+            stdout,
+            progress,
+        };
+
+        // Typing a password can take longer than any command
+        let authentication = RunControl {
+            timeout: None,
+            ..control.clone()
+        };
+
+        match session.read_reply(&authentication)?.as_str() {
+            READY => Ok(session),
+            reply => Err(format!("Unexpected helper reply {reply}").into()),
+        }
+    }
+
+    pub fn run(
+        &mut self,
+        verb: Verb,
+        package_name: &str,
+        control: &RunControl,
+    ) -> Result<(), SessionError> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or("Helper stdin closed".to_string())?;
+        writeln!(stdin, "{} {package_name}", verb.name())
+            .and_then(|_| stdin.flush())
+            .map_err(|e| format!("Helper communication failed: {e}"))?;
+
+        let reply = self.read_reply(control)?;
+        match reply.strip_prefix(ERROR_PREFIX) {
+            Some(message) => Err(message.to_string().into()),
+            None if reply == OK => Ok(()),
+            None => Err(format!("Unexpected helper reply {reply}").into()),
+        }
+    }
+
+    fn read_reply(&mut self, control: &RunControl) -> Result<String, SessionError> {
+        match control.read_line(&mut self.child, &self.stdout) {
+            Ok(Some(line)) => Ok(line.trim_end().to_string()),
+            Ok(None) => Err("Helper exited, authentication may have failed"
+                .to_string()
+                .into()),
+            Err(error) => {
+                self.stdin = None;
+                Err(SessionError::Stopped(error))
+            }
+        }
+    }
+}
+
+impl Drop for HelperSession {
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+        let _ = self.child.wait();
+
+        if let Some(progress) = self.progress.take() {
+            let _ = progress.join();
+        }
+    }
+}
+
+// Lets pkexec run the helper installed at helper_path, keeping the
+// authorization for a while like sudo does
+pub fn polkit_policy(helper_path: &Path) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE policyconfig PUBLIC "-//freedesktop//DTD PolicyKit Policy Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/PolicyKit/1/policyconfig.dtd">
+<policyconfig>
+  <action id="{POLKIT_ACTION_ID}">
+    <description>Manage packages with pacman</description>
+    <message>Authentication is required to install, remove or update packages</message>
+    <defaults>
+      <allow_any>auth_admin</allow_any>
+      <allow_inactive>auth_admin</allow_inactive>
+      <allow_active>auth_admin_keep</allow_active>
+    </defaults>
+    <annotate key="org.freedesktop.policykit.exec.path">{}</annotate>
+  </action>
+</policyconfig>
+"#,
+        helper_path.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::{FAKE_TOOL, fake_program};
+    use std::fs;
+
+    #[test]
+    fn test_serve_only_runs_whitelisted_commands() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let pacman = fake_program(temp_dir.path(), "pacman", FAKE_TOOL);
+        let input = "install ripgrep\n\
+                     remove --cascade\n\
+                     sysupgrade ripgrep\n\
+                     update broken\n\
+                     remove zsh\n";
+        let mut output = Vec::new();
+
+        serve(
+            input.as_bytes(),
+            &mut output,
+            &pacman,
+            &PacmanOptions {
+                root: Some("/mnt".into()),
+                ..PacmanOptions::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output)
+                .unwrap()
+                .lines()
+                .collect::<Vec<_>>(),
+            vec![
+                "ready",
+                "ok",
+                "error Invalid package name --cascade",
+                "error Unknown command sysupgrade",
+                "error exit code 1",
+                "ok",
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(pacman.with_extension("calls")).unwrap(),
+            "--root /mnt -S ripgrep --noconfirm\n\
+             --root /mnt -Sy broken --noconfirm\n\
+             --root /mnt -Rns zsh --noconfirm\n"
+        );
+    }
+
+    #[test]
+    fn test_session_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let helper = fake_program(
+            temp_dir.path(),
+            HELPER_NAME,
+            "#!/bin/sh
+echo ready
+while read -r verb package; do
+    if [ \"$package\" = broken ]; then echo \"error exit code 1\"; else echo ok; fi
+done
+",
+        );
+
+        let control = RunControl::default();
+        let mut session = HelperSession::start(Command::new(&helper), None, &control).unwrap();
+
+        assert!(session.run(Verb::Install, "ripgrep", &control).is_ok());
+        assert!(matches!(
+            session.run(Verb::Remove, "broken", &control),
+            Err(SessionError::Failed(message)) if message == "exit code 1"
+        ));
+    }
+
+    #[test]
+    fn test_session_fails_when_helper_exits() {
+        let result = HelperSession::start(Command::new("false"), None, &RunControl::default());
+
+        assert!(matches!(result, Err(SessionError::Failed(_))));
+    }
+
+    #[test]
+    fn test_polkit_policy_points_to_helper() {
+        let policy = polkit_policy(Path::new("/usr/lib/pazckager/pazckager-pacman-helper"));
+
+        assert!(policy.contains(&format!("<action id=\"{POLKIT_ACTION_ID}\">")));
+        assert!(policy.contains(
+            "<annotate key=\"org.freedesktop.policykit.exec.path\">/usr/lib/pazckager/pazckager-pacman-helper</annotate>"
+        ));
+    }
+}
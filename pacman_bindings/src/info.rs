@@ -0,0 +1,133 @@
+use std::collections::BTreeMap;
+
+use pazckager_core::models::PackageInfo;
+
+// "Depends On      : gcc-libs  pcre2", values that don't fit on the line go
+// on indented lines below it. Only the first package is read, -Si prints one
+// per repository
+fn parse_fields(output: &str) -> BTreeMap<&str, String> {
+    let mut fields: BTreeMap<&str, String> = BTreeMap::new();
+    let mut last_key = None;
+
+    for line in output.lines() {
+        if line.trim().is_empty() {
+            if last_key.is_some() {
+                break;
+            }
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            if let Some(value) = last_key.and_then(|key| fields.get_mut(key)) {
+                value.push_str("  ");
+                value.push_str(line.trim());
+            }
+            continue;
+        }
+
+        if let Some((key, value)) = line.split_once(" : ").or_else(|| line.split_once(" :")) {
+            let key = key.trim();
+            fields.insert(key, value.trim().to_string());
+            last_key = Some(key);
+        }
+    }
+
+    fields
+}
+
+fn value(fields: &BTreeMap<&str, String>, key: &str) -> Option<String> {
+    fields
+        .get(key)
+        .filter(|value| !value.is_empty() && *value != "None")
+        .cloned()
+}
+
+// List items are separated by two spaces, single spaces belong to the item
+fn list(fields: &BTreeMap<&str, String>, key: &str) -> Vec<String> {
+    value(fields, key)
+        .map(|value| {
+            value
+                .split("  ")
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Merges the local database, what is installed, with the sync database, which
+// knows the repository and packages that aren't installed
+pub fn package_info(local: Option<&str>, sync: Option<&str>) -> Option<PackageInfo> {
+    let local = local.map(parse_fields).unwrap_or_default();
+    let sync = sync.map(parse_fields).unwrap_or_default();
+
+    if local.is_empty() && sync.is_empty() {
+        return None;
+    }
+
+    let field = |key: &str| value(&local, key).or_else(|| value(&sync, key));
+    let list_field = |key: &str| {
+        let items = list(&local, key);
+        if items.is_empty() {
+            list(&sync, key)
+        } else {
+            items
+        }
+    };
+
+    Some(PackageInfo {
+        description: field("Description"),
+        version: field("Version"),
+        repository: value(&sync, "Repository"),
+        url: field("URL"),
+        licenses: list_field("Licenses"),
+        installed_size: field("Installed Size"),
+        dependencies: list_field("Depends On"),
+        required_by: list(&local, "Required By"),
+        install_date: value(&local, "Install Date"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACMAN_QI: &str = include_str!("../fixtures/pacman_qi.txt");
+    const PACMAN_SI: &str = include_str!("../fixtures/pacman_si.txt");
+
+    #[test]
+    fn test_installed_package_info() {
+        assert_eq!(
+            package_info(Some(PACMAN_QI), Some(PACMAN_SI)),
+            Some(PackageInfo {
+                description: Some(
+                    "A search tool that combines the usability of ag with the raw speed of grep"
+                        .to_string()
+                ),
+                version: Some("14.1.1-1".to_string()),
+                repository: Some("extra".to_string()),
+                url: Some("https://github.com/BurntSushi/ripgrep".to_string()),
+                licenses: vec!["MIT".to_string(), "Unlicense".to_string()],
+                installed_size: Some("4.53 MiB".to_string()),
+                dependencies: vec![
+                    "gcc-libs".to_string(),
+                    "glibc".to_string(),
+                    "pcre2".to_string()
+                ],
+                required_by: vec![],
+                install_date: Some("Sat 12 Oct 2024 10:02:31 AM CEST".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_sync_only_package_info() {
+        let info = package_info(None, Some(PACMAN_SI)).unwrap();
+
+        assert_eq!(info.version, Some("14.1.1-2".to_string()));
+        assert_eq!(info.repository, Some("extra".to_string()));
+        assert_eq!(info.install_date, None);
+        assert_eq!(package_info(None, None), None);
+    }
+}
@@ -1,35 +1,199 @@
-use pazckager_core::models::{InstallationTools, RawPackageData};
-use pazckager_core::traits::{InstallationTool, ToolError, ToolResult};
+use graph::DependencyGraph;
+use helper::{HELPER_NAME, HelperSession, SessionError, Verb};
+use pazckager_core::models::{
+    InstallationTools, PackageData, PackageInfo, PackageMetadata, PackageUsage, RawPackageData,
+};
+pub use pazckager_core::permissions::PermissionMethod;
+use pazckager_core::program::{RunControl, find_program, listing_output, program_version};
+use pazckager_core::traits::{InstallationTool, ToolCapability, ToolError, ToolResult};
+use progress::{ProgressEvent, ProgressHandler, forward_progress};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::Arc;
 
-#[derive(Debug, Clone, Copy)]
-pub enum PermissionMethod {
-    Sudo,
-    Pkexec,
-}
+pub use options::{PacmanOptions, Removal};
+
+pub mod files;
+pub mod graph;
+pub mod helper;
+mod info;
+mod options;
+pub mod progress;
+
+// Package metadata key holding the package installed for a virtual package,
+// recorded on the first install so other machines get the same provider
+pub const PROVIDER_KEY: &str = "provider";
 
 pub struct PacmanInstaller {
     permission_method: PermissionMethod,
+    options: PacmanOptions,
+    program: PathBuf,
+    helper: Option<PathBuf>,
+    session: Option<HelperSession>,
+    control: RunControl,
+    progress: Option<ProgressHandler>,
 }
 
 impl PacmanInstaller {
     pub fn new(permission_method: PermissionMethod) -> Self {
-        Self { permission_method }
+        Self {
+            permission_method,
+            options: PacmanOptions::default(),
+            program: PathBuf::from("pacman"),
+            helper: None,
+            session: None,
+            control: RunControl::default(),
+            progress: None,
+        }
     }
 
-    fn create_command_with_permissions(&self, command: &str) -> Command {
-        match self.permission_method {
-            PermissionMethod::Sudo => {
-                let mut cmd = Command::new("sudo");
-                cmd.arg(command);
-                cmd
-            }
-            PermissionMethod::Pkexec => {
-                let mut cmd = Command::new("pkexec");
-                cmd.arg(command);
-                cmd
+    pub fn with_options(mut self, options: PacmanOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    // Helper used for batches, by default it is looked up in PATH and every
+    // package is run through the permission method when it is missing
+    pub fn with_helper(mut self, helper: impl Into<PathBuf>) -> Self {
+        self.helper = Some(helper.into());
+        self
+    }
+
+    // Pacman output is parsed into events for the handler instead of being
+    // shown, except for interactive installers that need the terminal
+    pub fn with_progress(
+        mut self,
+        handler: impl Fn(ProgressEvent) + Send + Sync + 'static,
+    ) -> Self {
+        self.progress = Some(Arc::new(handler));
+        self
+    }
+
+    fn parsed_progress(&self) -> Option<ProgressHandler> {
+        self.progress.clone().filter(|_| !self.options.interactive)
+    }
+
+    // The permission methods don't all keep the environment, parsed output
+    // gets its C locale through env
+    fn create_command_with_permissions(&self) -> Command {
+        let mut command = if self.parsed_progress().is_some() {
+            let mut command = self.permission_method.command("env");
+            command.arg("LC_ALL=C").arg(&self.program);
+            command
+        } else {
+            self.permission_method.command(&self.program)
+        };
+        command.args(self.options.global_args());
+        command
+    }
+
+    pub fn dependency_graph(&self) -> ToolResult<DependencyGraph> {
+        let local_db = self.options.local_db();
+
+        DependencyGraph::read(&local_db).map_err(|e| {
+            ToolError::QueryingPackage(format!(
+                "Failed to read the pacman database {}: {e}",
+                local_db.display()
+            ))
+        })
+    }
+
+    // Installed packages owning a path, given on the host or inside the root
+    pub fn file_owners(&self, path: &Path) -> ToolResult<Vec<String>> {
+        let local_db = self.options.local_db();
+        let root = self.options.root.as_deref().unwrap_or(Path::new("/"));
+
+        files::file_owners(&local_db, root, path).map_err(|e| {
+            ToolError::QueryingPackage(format!(
+                "Failed to read the pacman database {}: {e}",
+                local_db.display()
+            ))
+        })
+    }
+
+    // Output of a query like -Qi, None when pacman doesn't know the package
+    fn query(&self, flag: &str, package_name: &str) -> ToolResult<Option<String>> {
+        let output = Command::new(&self.program)
+            .env("LC_ALL", "C")
+            .args(self.options.global_args())
+            .args([flag, package_name])
+            .output()
+            .map_err(|e| ToolError::QueryingPackage(format!("Failed to execute pacman: {e}")))?;
+
+        Ok(output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).to_string()))
+    }
+
+    // The helper batches run through. Interactive installers keep running
+    // pacman on the terminal, and installers with options the helper refuses
+    // or without the helper installed authenticate every command
+    fn batch_helper(&self) -> Option<PathBuf> {
+        if self.options.interactive || self.options.check_helper_options().is_err() {
+            return None;
+        }
+
+        find_program(self.helper.as_deref().unwrap_or(Path::new(HELPER_NAME)))
+    }
+
+    fn run(
+        &mut self,
+        verb: Verb,
+        package_name: &str,
+        action: &str,
+        to_error: fn(String) -> ToolError,
+    ) -> ToolResult<()> {
+        // The helper shares the terminal process group, Ctrl-C stops it along
+        // with its pacman. A stopped helper can't be used for the rest of the
+        // batch, the packages left run pacman directly
+        if let Some(session) = &mut self.session {
+            let result = session.run(verb, package_name, &self.control);
+            let cancelled = self.control.cancellation.is_cancelled();
+
+            return result.map_err(|e| match e {
+                SessionError::Stopped(e) => {
+                    self.session = None;
+                    e.into_tool_error("pacman", to_error)
+                }
+                SessionError::Failed(_) if cancelled => ToolError::Cancelled("pacman".to_string()),
+                SessionError::Failed(e) => to_error(format!(
+                    "Pacman failed to {action} package {package_name}: {e}"
+                )),
+            });
+        }
+
+        let mut command = self.create_command_with_permissions();
+        command.args(self.options.operation_args(verb, package_name));
+
+        let progress = match self.parsed_progress() {
+            Some(handler) => {
+                let (output, input) = io::pipe()
+                    .map_err(|e| to_error(format!("Failed to capture pacman output: {e}")))?;
+                command.stdout(input);
+                Some(forward_progress(output, handler))
             }
+            None => None,
+        };
+
+        let result = self
+            .control
+            .run(&mut command, "pacman", package_name, action, to_error);
+
+        // The command keeps the write end of the pipe open until it is dropped
+        drop(command);
+        if let Some(progress) = progress {
+            let _ = progress.join();
         }
+
+        result
     }
 }
 
@@ -38,91 +202,171 @@ impl InstallationTool for PacmanInstaller {
         InstallationTools::Pacman
     }
 
-    fn install_package(&mut self, package_name: &str) -> ToolResult<()> {
-        let mut command = self.create_command_with_permissions("pacman");
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
 
-        let status = command
-            .args(["-S", package_name, "--noconfirm"])
-            .status()
-            .map_err(|e| {
-                ToolError::InstallingPackage(format!("Failed to execute pacman: {}", e))
-            })?;
+    fn is_available(&self) -> bool {
+        find_program(&self.program).is_some()
+    }
 
-        if status.success() {
-            Ok(())
-        } else {
-            Err(ToolError::InstallingPackage(format!(
-                "Pacman failed to install package {} with exit code: {}",
-                package_name,
-                status.code().unwrap_or(-1)
-            )))
+    fn version(&self) -> Option<String> {
+        // The first line carries the logo next to "Pacman v6.1.0 - libalpm v14.0.0"
+        let version = program_version(&self.program)?;
+        let (_, version) = version.split_once("Pacman ")?;
+
+        Some(version.split(" - ").next()?.to_string())
+    }
+
+    fn capabilities(&self) -> BTreeSet<ToolCapability> {
+        let mut capabilities = BTreeSet::from([ToolCapability::Info]);
+
+        if self.batch_helper().is_some() {
+            capabilities.insert(ToolCapability::Batch);
         }
+
+        capabilities
     }
 
-    fn delete_package(&mut self, package_name: &str) -> ToolResult<()> {
-        let mut command = self.create_command_with_permissions("pacman");
+    // Starts the privileged helper so the batch only authenticates once
+    fn begin_batch(&mut self) -> ToolResult<()> {
+        let Some(helper) = self.batch_helper() else {
+            return Ok(());
+        };
 
-        let status = command
-            .args(["-Rns", package_name, "--noconfirm"])
-            .status()
-            .map_err(|e| ToolError::DeletingPackage(format!("Failed to execute pacman: {}", e)))?;
+        if self.session.is_none() {
+            let mut command = self.permission_method.command(helper);
+            command.args(self.options.helper_args());
 
-        if status.success() {
-            Ok(())
-        } else {
-            Err(ToolError::DeletingPackage(format!(
-                "Pacman failed to remove package {} with exit code: {}",
-                package_name,
-                status.code().unwrap_or(-1)
-            )))
+            let session = HelperSession::start(command, self.parsed_progress(), &self.control)
+                .map_err(|e| match e {
+                    SessionError::Failed(e) => ToolError::StartingSession(e),
+                    SessionError::Stopped(e) => {
+                        e.into_tool_error("pacman", ToolError::StartingSession)
+                    }
+                })?;
+            self.session = Some(session);
         }
+
+        Ok(())
+    }
+
+    fn end_batch(&mut self) -> ToolResult<()> {
+        self.session = None;
+        Ok(())
     }
 
-    fn update_package(&mut self, package_name: &str) -> ToolResult<()> {
-        let mut command = self.create_command_with_permissions("pacman");
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let target = self.installed_name(package).to_string();
 
-        let status = command
-            .args(["-Sy", package_name, "--noconfirm"])
-            .status()
-            .map_err(|e| ToolError::UpdatingPackage(format!("Failed to execute pacman: {}", e)))?;
+        self.run(
+            Verb::Install,
+            &target,
+            "install",
+            ToolError::InstallingPackage,
+        )
+    }
 
-        if status.success() {
-            Ok(())
-        } else {
-            Err(ToolError::UpdatingPackage(format!(
-                "Pacman failed to update package {} with exit code: {}",
-                package_name,
-                status.code().unwrap_or(-1)
-            )))
-        }
+    fn installed_name<'a>(&self, package: &'a PackageData) -> &'a str {
+        package
+            .metadata
+            .get(PROVIDER_KEY)
+            .unwrap_or(&package.package_name)
     }
 
-    fn get_packages(&self) -> Vec<RawPackageData> {
-        let mut command = Command::new("pacman");
-
-        match command.args(["-Qe"]).output() {
-            Ok(output) if output.status.success() => {
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                stdout
-                    .lines()
-                    .map(|line| {
-                        let parts: Vec<&str> = line.split_whitespace().collect();
-                        RawPackageData {
-                            package_name: parts.first().unwrap_or(&"").to_string(),
-                            category_name: String::new(),
-                            installation_tool: InstallationTools::Pacman,
-                        }
-                    })
-                    .collect()
+    // Querying a virtual package gives the package that provides it
+    fn installed_metadata(&self, package: &PackageData) -> PackageMetadata {
+        let mut metadata = package.metadata.clone();
+
+        if !metadata.contains_key(PROVIDER_KEY) {
+            let mut command = Command::new(&self.program);
+            command
+                .args(self.options.global_args())
+                .args(["-Qq", &package.package_name]);
+
+            let provider = listing_output(command, &self.control)
+                .ok()
+                .and_then(|output| output.lines().next().map(str::to_string))
+                .filter(|provider| !provider.is_empty() && provider != &package.package_name);
+
+            if let Some(provider) = provider {
+                metadata.insert(PROVIDER_KEY.to_string(), provider);
             }
-            _ => Vec::new(),
         }
+
+        metadata
+    }
+
+    // The local database describes the installed package, the sync database
+    // adds its repository
+    fn info(&self, package: &PackageData) -> ToolResult<Option<PackageInfo>> {
+        let target = self.installed_name(package);
+
+        let local = self.query("-Qi", target)?;
+        let sync = self.query("-Si", target)?;
+
+        Ok(info::package_info(local.as_deref(), sync.as_deref()))
+    }
+
+    fn disk_usage(&self) -> ToolResult<Vec<PackageUsage>> {
+        let graph = self.dependency_graph()?;
+
+        Ok(graph
+            .packages()
+            .map(|package| PackageUsage {
+                package_name: package.name.clone(),
+                size: package.size,
+                dependencies: graph
+                    .dependencies(&package.name)
+                    .into_iter()
+                    .map(str::to_string)
+                    .collect(),
+            })
+            .collect())
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let target = self.installed_name(package).to_string();
+
+        self.run(Verb::Remove, &target, "remove", ToolError::DeletingPackage)
+    }
+
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let target = self.installed_name(package).to_string();
+
+        self.run(Verb::Update, &target, "update", ToolError::UpdatingPackage)
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        let mut command = Command::new(&self.program);
+        command.args(self.options.global_args()).arg("-Qe");
+
+        Ok(listing_output(command, &self.control)?
+            .lines()
+            .filter_map(|line| {
+                let package_name = line.split_whitespace().next()?;
+
+                Some(RawPackageData {
+                    package_name: package_name.to_string(),
+                    category_name: String::new(),
+                    installation_tool: InstallationTools::Pacman,
+                    metadata: Default::default(),
+                })
+            })
+            .collect())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use pazckager_core::testing::{
+        FAKE_TOOL, calls, check_capabilities, fake_program, test_package,
+    };
+    use std::fs;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_pacman_installer_creation() {
@@ -130,11 +374,292 @@ mod tests {
         assert_eq!(installer.get_type(), InstallationTools::Pacman);
     }
 
+    #[test]
+    fn test_root_is_passed_to_pacman() {
+        let installer = PacmanInstaller::new(PermissionMethod::Sudo).with_options(PacmanOptions {
+            root: Some("/mnt/chroot".into()),
+            ..PacmanOptions::default()
+        });
+        let command = installer.create_command_with_permissions();
+
+        assert_eq!(
+            command.get_args().collect::<Vec<_>>(),
+            vec!["pacman", "--root", "/mnt/chroot"]
+        );
+    }
+
+    #[test]
+    fn test_batch_runs_through_one_helper_session() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let helper = fake_program(
+            temp_dir.path(),
+            HELPER_NAME,
+            "#!/bin/sh
+echo \"start $*\" >> \"$0.calls\"
+echo ready
+while read -r line; do echo \"$line\" >> \"$0.calls\"; echo ok; done
+",
+        );
+
+        let mut installer = PacmanInstaller::new(PermissionMethod::Root)
+            .with_options(PacmanOptions {
+                root: Some("/".into()),
+                removal: Removal::Unneeded,
+                ..PacmanOptions::default()
+            })
+            .with_helper(&helper);
+
+        installer.begin_batch()?;
+        installer.install_package(&test_package(InstallationTools::Pacman, "ripgrep", &[]))?;
+        installer.delete_package(&test_package(InstallationTools::Pacman, "zsh", &[]))?;
+        installer.end_batch()?;
+
+        assert_eq!(
+            fs::read_to_string(helper.with_extension("calls")).unwrap(),
+            "start --root / --removal unneeded\ninstall ripgrep\nremove zsh\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_options_the_helper_refuses_run_pacman_directly() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let helper = fake_program(temp_dir.path(), HELPER_NAME, FAKE_TOOL);
+        let program = fake_program(temp_dir.path(), "pacman", FAKE_TOOL);
+
+        let mut installer = PacmanInstaller::new(PermissionMethod::Root)
+            .with_options(PacmanOptions {
+                config: Some("/etc/pacman-chroot.conf".into()),
+                ..PacmanOptions::default()
+            })
+            .with_program(&program)
+            .with_helper(&helper);
+
+        installer.begin_batch()?;
+        installer.install_package(&test_package(InstallationTools::Pacman, "ripgrep", &[]))?;
+        installer.end_batch()?;
+
+        assert!(calls(&helper).is_empty());
+        assert_eq!(
+            calls(&program),
+            vec!["--config /etc/pacman-chroot.conf -S ripgrep --noconfirm"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(
+            temp_dir.path(),
+            "pacman",
+            &format!(
+                "#!/bin/sh\ncase \"$1\" in\n    -Qi) cat {} ;;\n    *) exit 1 ;;\nesac\n",
+                Path::new(env!("CARGO_MANIFEST_DIR"))
+                    .join("fixtures/pacman_qi.txt")
+                    .display()
+            ),
+        );
+        let helper = fake_program(temp_dir.path(), HELPER_NAME, FAKE_TOOL);
+        let installer = PacmanInstaller::new(PermissionMethod::Root)
+            .with_program(&program)
+            .with_helper(&helper);
+        let interactive = PacmanInstaller::new(PermissionMethod::Root)
+            .with_options(PacmanOptions {
+                interactive: true,
+                ..PacmanOptions::default()
+            })
+            .with_helper(&helper);
+
+        assert_eq!(
+            installer.capabilities(),
+            BTreeSet::from([ToolCapability::Batch, ToolCapability::Info])
+        );
+        assert_eq!(
+            interactive.capabilities(),
+            BTreeSet::from([ToolCapability::Info])
+        );
+        assert_eq!(
+            PacmanInstaller::new(PermissionMethod::Root)
+                .with_helper(temp_dir.path().join("missing"))
+                .capabilities(),
+            BTreeSet::from([ToolCapability::Info])
+        );
+        check_capabilities(
+            &installer,
+            &test_package(InstallationTools::Pacman, "ripgrep", &[]),
+        );
+    }
+
+    #[test]
+    fn test_hanging_pacman_times_out() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "pacman", "#!/bin/sh\nsleep 5\n");
+
+        let mut installer = PacmanInstaller::new(PermissionMethod::Root).with_program(&program);
+        installer.set_run_control(RunControl {
+            timeout: Some(Duration::from_millis(100)),
+            ..RunControl::default()
+        });
+
+        assert!(matches!(
+            installer.install_package(&test_package(InstallationTools::Pacman, "ripgrep", &[])),
+            Err(ToolError::TimedOut(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_hanging_helper_session_times_out() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let helper = fake_program(
+            temp_dir.path(),
+            HELPER_NAME,
+            "#!/bin/sh\necho ready\nread -r line\nexec sleep 5\n",
+        );
+        let program = fake_program(temp_dir.path(), "pacman", FAKE_TOOL);
+
+        let mut installer = PacmanInstaller::new(PermissionMethod::Root)
+            .with_program(&program)
+            .with_helper(&helper);
+        installer.set_run_control(RunControl {
+            timeout: Some(Duration::from_millis(100)),
+            ..RunControl::default()
+        });
+        let started = Instant::now();
+
+        installer.begin_batch()?;
+        assert!(matches!(
+            installer.install_package(&test_package(InstallationTools::Pacman, "ripgrep", &[])),
+            Err(ToolError::TimedOut(_, _))
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // The stopped helper is left behind
+        installer.install_package(&test_package(InstallationTools::Pacman, "zsh", &[]))?;
+        installer.end_batch()?;
+        assert_eq!(calls(&program), vec!["-S zsh --noconfirm"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelling_stops_the_helper_session() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let helper = fake_program(
+            temp_dir.path(),
+            HELPER_NAME,
+            "#!/bin/sh\necho ready\nread -r line\nexec sleep 5\n",
+        );
+
+        let mut installer = PacmanInstaller::new(PermissionMethod::Root).with_helper(&helper);
+        let control = RunControl::default();
+        installer.set_run_control(control.clone());
+        installer.begin_batch()?;
+
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            control.cancellation.cancel();
+        });
+        let result =
+            installer.install_package(&test_package(InstallationTools::Pacman, "ripgrep", &[]));
+        canceller.join().unwrap();
+
+        assert!(matches!(result, Err(ToolError::Cancelled(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_progress_is_parsed_in_c_locale() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(
+            temp_dir.path(),
+            "pacman",
+            "#!/bin/sh
+echo \"LC_ALL=$LC_ALL\" >> \"$0.calls\"
+echo 'Packages (1) ripgrep-14.1.1-1'
+echo 'installing ripgrep...'
+",
+        );
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let mut installer = PacmanInstaller::new(PermissionMethod::Root)
+            .with_program(&program)
+            .with_progress(move |event| sink.lock().unwrap().push(event));
+
+        installer.install_package(&test_package(InstallationTools::Pacman, "ripgrep", &[]))?;
+
+        assert_eq!(
+            fs::read_to_string(program.with_extension("calls")).unwrap(),
+            "LC_ALL=C\n"
+        );
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![ProgressEvent::Package {
+                operation: progress::PackageOperation::Installing,
+                package: "ripgrep".to_string(),
+                current: 1,
+                total: 1,
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_provider_is_recorded_and_reused() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(
+            temp_dir.path(),
+            "pacman",
+            "#!/bin/sh
+echo \"$@\" >> \"$0.calls\"
+case \"$*\" in
+    \"-Qq jack\") echo pipewire-jack ;;
+    -Qq*) echo \"$2\" ;;
+esac
+",
+        );
+        let mut installer = PacmanInstaller::new(PermissionMethod::Root).with_program(&program);
+
+        let jack = test_package(InstallationTools::Pacman, "jack", &[]);
+        installer.install_package(&jack)?;
+        let metadata = installer.installed_metadata(&jack);
+        assert_eq!(metadata[PROVIDER_KEY], "pipewire-jack");
+        assert!(
+            !installer
+                .installed_metadata(&test_package(InstallationTools::Pacman, "ripgrep", &[]))
+                .contains_key(PROVIDER_KEY)
+        );
+
+        let jack = PackageData { metadata, ..jack };
+        installer.install_package(&jack)?;
+        installer.update_package(&jack)?;
+        installer.delete_package(&jack)?;
+
+        assert_eq!(
+            calls(&program),
+            vec![
+                "-S jack --noconfirm",
+                "-Qq jack",
+                "-Qq ripgrep",
+                "-S pipewire-jack --noconfirm",
+                "-Sy pipewire-jack --noconfirm",
+                "-Rns pipewire-jack --noconfirm",
+            ]
+        );
+
+        Ok(())
+    }
+
     #[test]
     #[ignore]
     fn test_get_packages() {
         let installer = PacmanInstaller::new(PermissionMethod::Sudo);
-        let packages = installer.get_packages();
+        let packages = installer.get_packages().unwrap();
         assert!(!packages.is_empty(), "Should return some packages");
     }
 }
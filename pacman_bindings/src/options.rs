@@ -0,0 +1,347 @@
+use serde::Deserialize;
+use std::ffi::OsString;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use crate::helper::Verb;
+
+// How much `remove` takes away with the package
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Removal {
+    // -R, only the package
+    Package,
+    // -Rs, also the dependencies nothing else needs
+    Unneeded,
+    // -Rns, also the saved configuration files
+    #[default]
+    Full,
+}
+
+impl Removal {
+    fn flag(&self) -> &'static str {
+        match self {
+            Removal::Package => "-R",
+            Removal::Unneeded => "-Rs",
+            Removal::Full => "-Rns",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Removal::Package => "package",
+            Removal::Unneeded => "unneeded",
+            Removal::Full => "full",
+        }
+    }
+
+    fn parse(name: &str) -> Option<Self> {
+        [Removal::Package, Removal::Unneeded, Removal::Full]
+            .into_iter()
+            .find(|removal| removal.name() == name)
+    }
+}
+
+// Per instance pacman settings, an instance with a root, dbpath and cachedir
+// manages a chroot or an image build root
+#[derive(Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct PacmanOptions {
+    pub root: Option<PathBuf>,
+    pub dbpath: Option<PathBuf>,
+    pub cachedir: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    // Skips reinstalling packages that are up to date
+    pub needed: bool,
+    // Globs of files installs may overwrite
+    pub overwrite: Vec<String>,
+    pub removal: Removal,
+    // Leaves out --noconfirm and lets pacman ask its questions, like which
+    // provider to install, on the terminal
+    pub interactive: bool,
+}
+
+impl PacmanOptions {
+    fn path_args(&self, paths: &[(&str, &Option<PathBuf>)]) -> Vec<OsString> {
+        let mut args = Vec::new();
+
+        for (flag, value) in paths {
+            if let Some(value) = value {
+                args.push(flag.into());
+                args.push(value.into());
+            }
+        }
+
+        args
+    }
+
+    // Options that pick the pacman installation, shared by every operation
+    pub fn global_args(&self) -> Vec<OsString> {
+        self.path_args(&[
+            ("--root", &self.root),
+            ("--dbpath", &self.dbpath),
+            ("--cachedir", &self.cachedir),
+            ("--config", &self.config),
+        ])
+    }
+
+    pub fn operation_args(&self, verb: Verb, package_name: &str) -> Vec<OsString> {
+        let mut args: Vec<OsString> = match verb {
+            Verb::Install => vec!["-S".into()],
+            Verb::Update => vec!["-Sy".into()],
+            Verb::Remove => vec![self.removal.flag().into()],
+        };
+
+        if verb != Verb::Remove {
+            if self.needed && verb == Verb::Install {
+                args.push("--needed".into());
+            }
+
+            for glob in &self.overwrite {
+                args.push("--overwrite".into());
+                args.push(glob.into());
+            }
+        }
+
+        args.push(package_name.into());
+        if !self.interactive {
+            args.push("--noconfirm".into());
+        }
+        args
+    }
+
+    // Like pacman, the database is inside the root unless dbpath is set. A
+    // DBPath from the pacman config file is not read
+    pub fn local_db(&self) -> PathBuf {
+        let dbpath = self.dbpath.clone().unwrap_or_else(|| {
+            self.root
+                .as_deref()
+                .unwrap_or(Path::new("/"))
+                .join("var/lib/pacman")
+        });
+
+        dbpath.join("local")
+    }
+
+    // The privileged helper receives the options as its arguments, only when
+    // check_helper_options accepts them
+    pub fn helper_args(&self) -> Vec<OsString> {
+        let mut args = self.path_args(&[
+            ("--root", &self.root),
+            ("--dbpath", &self.dbpath),
+            ("--cachedir", &self.cachedir),
+        ]);
+
+        if self.needed {
+            args.push("--needed".into());
+        }
+
+        args.push("--removal".into());
+        args.push(self.removal.name().into());
+        args
+    }
+
+    // The helper runs pacman as root for whoever starts it, so it takes no
+    // option that lets the caller run code or replace files as root. A config
+    // file names hooks and repositories and overwrite globs replace any file.
+    // Installers with options the helper refuses run pacman through their
+    // permission method, which authorizes the whole command line
+    pub fn check_helper_options(&self) -> Result<(), String> {
+        if self.config.is_some() {
+            return Err("The helper doesn't accept a pacman config file".to_string());
+        }
+
+        if !self.overwrite.is_empty() {
+            return Err("The helper doesn't accept overwrite globs".to_string());
+        }
+
+        [&self.root, &self.dbpath, &self.cachedir]
+            .into_iter()
+            .flatten()
+            .try_for_each(|path| check_trusted_directory(path))
+    }
+
+    pub fn from_helper_args(args: &[String]) -> Result<Self, String> {
+        let mut options = PacmanOptions::default();
+        let mut args = args.iter();
+
+        while let Some(flag) = args.next() {
+            if flag == "--needed" {
+                options.needed = true;
+                continue;
+            }
+
+            let value = args
+                .next()
+                .ok_or(format!("Missing value for {flag}"))?
+                .clone();
+
+            match flag.as_str() {
+                "--root" => options.root = Some(value.into()),
+                "--dbpath" => options.dbpath = Some(value.into()),
+                "--cachedir" => options.cachedir = Some(value.into()),
+                "--config" | "--overwrite" => {
+                    return Err(format!("The helper doesn't accept {flag}"));
+                }
+                "--removal" => {
+                    options.removal =
+                        Removal::parse(&value).ok_or(format!("Unknown removal {value}"))?
+                }
+                _ => return Err(format!("Unknown option {flag}")),
+            }
+        }
+
+        options.check_helper_options()?;
+        Ok(options)
+    }
+}
+
+// Pacman runs hooks and scriptlets from its root and trusts its database and
+// cache, so the directories given to the helper must be as safe as the helper
+// itself: absolute, without symlinks, and only root can write to them or to
+// any directory above them
+fn check_trusted_directory(path: &Path) -> Result<(), String> {
+    let canonical =
+        fs::canonicalize(path).map_err(|e| format!("Could not check {}: {e}", path.display()))?;
+
+    if canonical != path {
+        return Err(format!(
+            "{} must be an absolute path without symlinks",
+            path.display()
+        ));
+    }
+
+    for directory in path.ancestors() {
+        let metadata = fs::metadata(directory)
+            .map_err(|e| format!("Could not check {}: {e}", directory.display()))?;
+
+        if !metadata.is_dir() || metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+            return Err(format!(
+                "{} must be a directory only root can write to",
+                directory.display()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chroot_options() -> PacmanOptions {
+        PacmanOptions {
+            root: Some("/mnt/chroot".into()),
+            dbpath: Some("/mnt/chroot/var/lib/pacman".into()),
+            cachedir: None,
+            config: Some("/etc/pacman-chroot.conf".into()),
+            needed: true,
+            overwrite: vec!["/usr/lib/*".to_string()],
+            removal: Removal::Package,
+            interactive: false,
+        }
+    }
+
+    #[test]
+    fn test_operation_args() {
+        let options = chroot_options();
+
+        assert_eq!(
+            options.global_args(),
+            vec![
+                "--root",
+                "/mnt/chroot",
+                "--dbpath",
+                "/mnt/chroot/var/lib/pacman",
+                "--config",
+                "/etc/pacman-chroot.conf",
+            ]
+        );
+        assert_eq!(
+            options.operation_args(Verb::Install, "ripgrep"),
+            vec![
+                "-S",
+                "--needed",
+                "--overwrite",
+                "/usr/lib/*",
+                "ripgrep",
+                "--noconfirm"
+            ]
+        );
+        assert_eq!(
+            options.operation_args(Verb::Remove, "ripgrep"),
+            vec!["-R", "ripgrep", "--noconfirm"]
+        );
+        assert_eq!(
+            PacmanOptions::default().operation_args(Verb::Remove, "ripgrep"),
+            vec!["-Rns", "ripgrep", "--noconfirm"]
+        );
+        assert_eq!(
+            PacmanOptions {
+                interactive: true,
+                ..PacmanOptions::default()
+            }
+            .operation_args(Verb::Update, "ripgrep"),
+            vec!["-Sy", "ripgrep"]
+        );
+    }
+
+    #[test]
+    fn test_local_db() {
+        assert_eq!(
+            PacmanOptions::default().local_db(),
+            PathBuf::from("/var/lib/pacman/local")
+        );
+        assert_eq!(
+            PacmanOptions {
+                root: Some("/mnt/chroot".into()),
+                ..PacmanOptions::default()
+            }
+            .local_db(),
+            PathBuf::from("/mnt/chroot/var/lib/pacman/local")
+        );
+        assert_eq!(
+            chroot_options().local_db(),
+            PathBuf::from("/mnt/chroot/var/lib/pacman/local")
+        );
+    }
+
+    #[test]
+    fn test_helper_args_round_trip() {
+        let options = PacmanOptions {
+            root: Some("/".into()),
+            dbpath: Some("/usr".into()),
+            needed: true,
+            removal: Removal::Package,
+            ..PacmanOptions::default()
+        };
+        let args: Vec<String> = options
+            .helper_args()
+            .into_iter()
+            .map(|arg| arg.into_string().unwrap())
+            .collect();
+
+        assert_eq!(PacmanOptions::from_helper_args(&args), Ok(options));
+        assert!(PacmanOptions::from_helper_args(&["--sysroot".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_helper_refuses_unsafe_options() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let helper_args = |args: &[&str]| {
+            PacmanOptions::from_helper_args(
+                &args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>(),
+            )
+        };
+
+        assert!(chroot_options().check_helper_options().is_err());
+        assert!(helper_args(&["--config", "/etc/pacman.conf"]).is_err());
+        assert!(helper_args(&["--overwrite", "*"]).is_err());
+        assert!(helper_args(&["--root", "usr"]).is_err());
+        assert!(helper_args(&["--root", "/usr/../usr"]).is_err());
+        assert!(helper_args(&["--dbpath", "/nonexistent"]).is_err());
+        assert!(helper_args(&["--cachedir", temp_dir.path().to_str().unwrap()]).is_err());
+    }
+}
@@ -0,0 +1,444 @@
+// Turns pacman output into progress events. Without a terminal pacman prints
+// one line per step, on a terminal it redraws progress bars with carriage
+// returns, both forms are understood. The messages are only stable in the C
+// locale, pacman has to run with LC_ALL=C while it is being parsed.
+
+use std::io::{BufRead, BufReader, Read};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+pub type ProgressHandler = Arc<dyn Fn(ProgressEvent) + Send + Sync>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStep {
+    ResolvingDependencies,
+    CheckingDependencies,
+    CheckingConflicts,
+    CheckingKeys,
+    CheckingIntegrity,
+    LoadingFiles,
+    CheckingFileConflicts,
+    CheckingDiskSpace,
+    ProcessingChanges,
+    RunningHooks,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageOperation {
+    Installing,
+    Upgrading,
+    Reinstalling,
+    Downgrading,
+    Removing,
+}
+
+impl PackageOperation {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "installing" => Some(PackageOperation::Installing),
+            "upgrading" => Some(PackageOperation::Upgrading),
+            "reinstalling" => Some(PackageOperation::Reinstalling),
+            "downgrading" => Some(PackageOperation::Downgrading),
+            "removing" => Some(PackageOperation::Removing),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    // Sizes are only known from the progress bar, plain output just names
+    // the file being downloaded
+    Download {
+        package: String,
+        bytes: Option<u64>,
+        percent: Option<u8>,
+    },
+    Step(TransactionStep),
+    Package {
+        operation: PackageOperation,
+        package: String,
+        current: usize,
+        total: usize,
+    },
+    Hook {
+        current: usize,
+        total: usize,
+        description: String,
+    },
+    // Lines printed by a hook while it runs
+    HookOutput(String),
+    // Anything else, like install scriptlet messages
+    Output(String),
+}
+
+const STEPS: [(&str, TransactionStep); 11] = [
+    (
+        "resolving dependencies",
+        TransactionStep::ResolvingDependencies,
+    ),
+    (
+        "checking dependencies",
+        TransactionStep::CheckingDependencies,
+    ),
+    (
+        "looking for conflicting packages",
+        TransactionStep::CheckingConflicts,
+    ),
+    ("checking keyring", TransactionStep::CheckingKeys),
+    ("checking keys in keyring", TransactionStep::CheckingKeys),
+    (
+        "checking package integrity",
+        TransactionStep::CheckingIntegrity,
+    ),
+    ("loading package files", TransactionStep::LoadingFiles),
+    (
+        "checking for file conflicts",
+        TransactionStep::CheckingFileConflicts,
+    ),
+    (
+        "checking available disk space",
+        TransactionStep::CheckingDiskSpace,
+    ),
+    (
+        ":: Processing package changes",
+        TransactionStep::ProcessingChanges,
+    ),
+    (":: Running ", TransactionStep::RunningHooks),
+];
+
+// Pacman redraws a progress bar until it reaches 100%, only changes are
+// reported
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    // From "Packages (N) ...", plain output doesn't number the packages
+    total_packages: usize,
+    current_package: usize,
+    in_hooks: bool,
+    last_event: Option<ProgressEvent>,
+}
+
+impl ProgressParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse_line(&mut self, line: &str) -> Option<ProgressEvent> {
+        let event = self.event(line.trim_end())?;
+
+        if matches!(
+            event,
+            ProgressEvent::Output(_) | ProgressEvent::HookOutput(_)
+        ) {
+            return Some(event);
+        }
+
+        if self.last_event.as_ref() == Some(&event) {
+            return None;
+        }
+
+        self.last_event = Some(event.clone());
+        Some(event)
+    }
+
+    fn event(&mut self, line: &str) -> Option<ProgressEvent> {
+        if line.trim().is_empty() {
+            return None;
+        }
+
+        if let Some(packages) = line.strip_prefix("Packages (") {
+            let (total, _) = packages.split_once(')')?;
+            self.total_packages = total.parse().ok()?;
+            self.current_package = 0;
+            return None;
+        }
+
+        if let Some((_, step)) = STEPS.iter().find(|(prefix, _)| line.starts_with(prefix)) {
+            self.in_hooks = *step == TransactionStep::RunningHooks;
+            return Some(ProgressEvent::Step(*step));
+        }
+
+        // Prompts and the size summary
+        if line.starts_with(":: ")
+            || line.trim_start().starts_with("Total ")
+            || line.starts_with("Net Upgrade Size")
+        {
+            return None;
+        }
+
+        if let Some(event) = self.counted_line(line) {
+            return Some(event);
+        }
+
+        if let Some(package) = line.trim().strip_suffix(" downloading...") {
+            return Some(ProgressEvent::Download {
+                package: package.to_string(),
+                bytes: None,
+                percent: None,
+            });
+        }
+
+        if let Some(event) = download_bar(line) {
+            return Some(event);
+        }
+
+        if let Some(event) = self.package_line(line) {
+            return Some(event);
+        }
+
+        if self.in_hooks {
+            Some(ProgressEvent::HookOutput(line.to_string()))
+        } else {
+            Some(ProgressEvent::Output(line.to_string()))
+        }
+    }
+
+    // "(1/2) installing ripgrep [###] 100%" and "(1/2) Arming ConditionNeedsUpdate..."
+    fn counted_line(&mut self, line: &str) -> Option<ProgressEvent> {
+        let (counter, rest) = line.strip_prefix('(')?.split_once(") ")?;
+        let (current, total) = counter.split_once('/')?;
+        let current = current.trim().parse().ok()?;
+        let total = total.trim().parse().ok()?;
+        let text = strip_bar(rest);
+
+        if self.in_hooks {
+            return Some(ProgressEvent::Hook {
+                current,
+                total,
+                description: text.to_string(),
+            });
+        }
+
+        let (operation, package) = text.split_once(' ')?;
+        match PackageOperation::parse(operation) {
+            Some(operation) => Some(ProgressEvent::Package {
+                operation,
+                package: package.trim().to_string(),
+                current,
+                total,
+            }),
+            None => STEPS
+                .iter()
+                .find(|(prefix, _)| text.starts_with(prefix))
+                .map(|(_, step)| ProgressEvent::Step(*step)),
+        }
+    }
+
+    // "installing ripgrep...", numbered with the package count of the transaction
+    fn package_line(&mut self, line: &str) -> Option<ProgressEvent> {
+        let (operation, package) = line.strip_suffix("...")?.split_once(' ')?;
+        let operation = PackageOperation::parse(operation)?;
+
+        self.current_package += 1;
+        Some(ProgressEvent::Package {
+            operation,
+            package: package.to_string(),
+            current: self.current_package,
+            total: self.total_packages.max(self.current_package),
+        })
+    }
+}
+
+// Text before the progress bar, "[###---]  50%" is cut away
+fn strip_bar(text: &str) -> &str {
+    text.split_once(" [")
+        .map_or(text, |(text, _)| text)
+        .trim_end()
+        .trim_end_matches("...")
+}
+
+// " ripgrep-14.1.0-1-x86_64   1485.5 KiB  2.50 MiB/s 00:01 [######] 100%"
+fn download_bar(line: &str) -> Option<ProgressEvent> {
+    let (text, bar) = line.rsplit_once(" [")?;
+    let percent = bar
+        .rsplit_once(']')?
+        .1
+        .trim()
+        .strip_suffix('%')?
+        .parse()
+        .ok()?;
+
+    let mut fields = text.split_whitespace();
+    let package = fields.next()?.to_string();
+    let size: f64 = fields.next()?.parse().ok()?;
+    let unit = match fields.next()? {
+        "B" => 1.0,
+        "KiB" => 1024.0,
+        "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+
+    Some(ProgressEvent::Download {
+        package,
+        bytes: Some((size * unit) as u64),
+        percent: Some(percent),
+    })
+}
+
+// Reads pacman output on a thread until it is closed, bars are split on
+// their carriage returns so every redraw is parsed
+pub fn forward_progress(
+    output: impl Read + Send + 'static,
+    handler: ProgressHandler,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut parser = ProgressParser::new();
+        let mut reader = BufReader::new(output);
+        let mut buffer = Vec::new();
+
+        while let Ok(read) = reader.read_until(b'\n', &mut buffer) {
+            if read == 0 {
+                break;
+            }
+
+            for line in String::from_utf8_lossy(&buffer).split('\r') {
+                if let Some(event) = parser.parse_line(line) {
+                    handler(event);
+                }
+            }
+            buffer.clear();
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    const PACMAN_INSTALL: &str = include_str!("../fixtures/pacman_install.txt");
+    const PACMAN_INSTALL_BARS: &str = include_str!("../fixtures/pacman_install_bars.txt");
+    const PACMAN_REMOVE: &str = include_str!("../fixtures/pacman_remove.txt");
+
+    fn parse(output: &'static str) -> Vec<ProgressEvent> {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+
+        forward_progress(
+            output.as_bytes(),
+            Arc::new(move |event| sink.lock().unwrap().push(event)),
+        )
+        .join()
+        .unwrap();
+
+        Arc::try_unwrap(events).unwrap().into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_parse_plain_install() {
+        assert_eq!(
+            parse(PACMAN_INSTALL),
+            vec![
+                ProgressEvent::Step(TransactionStep::ResolvingDependencies),
+                ProgressEvent::Step(TransactionStep::CheckingConflicts),
+                ProgressEvent::Download {
+                    package: "ripgrep-14.1.1-1-x86_64".to_string(),
+                    bytes: None,
+                    percent: None,
+                },
+                ProgressEvent::Download {
+                    package: "oniguruma-6.9.9-1-x86_64".to_string(),
+                    bytes: None,
+                    percent: None,
+                },
+                ProgressEvent::Step(TransactionStep::CheckingKeys),
+                ProgressEvent::Step(TransactionStep::CheckingIntegrity),
+                ProgressEvent::Step(TransactionStep::LoadingFiles),
+                ProgressEvent::Step(TransactionStep::CheckingFileConflicts),
+                ProgressEvent::Step(TransactionStep::CheckingDiskSpace),
+                ProgressEvent::Step(TransactionStep::ProcessingChanges),
+                ProgressEvent::Package {
+                    operation: PackageOperation::Installing,
+                    package: "oniguruma".to_string(),
+                    current: 1,
+                    total: 2,
+                },
+                ProgressEvent::Package {
+                    operation: PackageOperation::Installing,
+                    package: "ripgrep".to_string(),
+                    current: 2,
+                    total: 2,
+                },
+                ProgressEvent::Output("Optional dependencies for ripgrep".to_string()),
+                ProgressEvent::Output("    bash-completion: shell completion".to_string()),
+                ProgressEvent::Step(TransactionStep::RunningHooks),
+                ProgressEvent::Hook {
+                    current: 1,
+                    total: 2,
+                    description: "Arming ConditionNeedsUpdate".to_string(),
+                },
+                ProgressEvent::Hook {
+                    current: 2,
+                    total: 2,
+                    description: "Updating the info directory file".to_string(),
+                },
+                ProgressEvent::HookOutput("install-info: No such file".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_progress_bars() {
+        assert_eq!(
+            parse(PACMAN_INSTALL_BARS),
+            vec![
+                ProgressEvent::Step(TransactionStep::ResolvingDependencies),
+                ProgressEvent::Step(TransactionStep::CheckingConflicts),
+                ProgressEvent::Download {
+                    package: "ripgrep-14.1.1-1-x86_64".to_string(),
+                    bytes: Some(0),
+                    percent: Some(0),
+                },
+                ProgressEvent::Download {
+                    package: "ripgrep-14.1.1-1-x86_64".to_string(),
+                    bytes: Some(786_432),
+                    percent: Some(50),
+                },
+                ProgressEvent::Download {
+                    package: "ripgrep-14.1.1-1-x86_64".to_string(),
+                    bytes: Some(1_572_864),
+                    percent: Some(100),
+                },
+                ProgressEvent::Step(TransactionStep::CheckingKeys),
+                ProgressEvent::Step(TransactionStep::CheckingIntegrity),
+                ProgressEvent::Step(TransactionStep::ProcessingChanges),
+                ProgressEvent::Package {
+                    operation: PackageOperation::Installing,
+                    package: "ripgrep".to_string(),
+                    current: 1,
+                    total: 1,
+                },
+                ProgressEvent::Step(TransactionStep::RunningHooks),
+                ProgressEvent::Hook {
+                    current: 1,
+                    total: 1,
+                    description: "Arming ConditionNeedsUpdate".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_remove() {
+        assert_eq!(
+            parse(PACMAN_REMOVE),
+            vec![
+                ProgressEvent::Step(TransactionStep::CheckingDependencies),
+                ProgressEvent::Step(TransactionStep::ProcessingChanges),
+                ProgressEvent::Package {
+                    operation: PackageOperation::Removing,
+                    package: "ripgrep".to_string(),
+                    current: 1,
+                    total: 1,
+                },
+                ProgressEvent::Step(TransactionStep::RunningHooks),
+                ProgressEvent::Hook {
+                    current: 1,
+                    total: 1,
+                    description: "Arming ConditionNeedsUpdate".to_string(),
+                },
+            ]
+        );
+    }
+}
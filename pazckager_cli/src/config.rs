@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::PathBuf;
+
+use clap::ValueEnum;
+use flatpak_bindings::FlatpakInstallation;
+use pacman_bindings::{PacmanOptions, PermissionMethod};
+use script_bindings::ScriptConfig;
+use serde::Deserialize;
+
+const CONFIG_PATH: &str = "~/.config/pazckager/config.toml";
+
+#[derive(Deserialize, ValueEnum, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreFormat {
+    #[default]
+    Json,
+    Toml,
+    Sqlite,
+}
+
+impl StoreFormat {
+    pub fn default_path(&self) -> &'static str {
+        match self {
+            StoreFormat::Json => "~/.local/share/pazckager_store.json",
+            StoreFormat::Toml => "~/.local/share/pazckager_store.toml",
+            StoreFormat::Sqlite => "~/.local/share/pazckager_store.db",
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    pub store_format: StoreFormat,
+    pub store_path: Option<String>,
+    // How pacman, apt and dnf gain root, detected when it is not set
+    pub permission_method: PermissionMethod,
+    // Seconds a single tool command may run before it is stopped
+    pub operation_timeout: Option<u64>,
+    pub pacman: PacmanOptions,
+    pub flatpak_installation: FlatpakInstallation,
+    pub pipx_home: Option<String>,
+    pub pipx_bin_dir: Option<String>,
+    pub npm_prefix: Option<String>,
+    pub script_installers: Vec<ScriptConfig>,
+    pub installers: Vec<InstallerInstance>,
+}
+
+// Tools without named instances. Apt and dnf manage the one system they run
+// on, cargo and nix have no settings to point an instance elsewhere yet and
+// script installers are already named by their own config
+const SINGLE_INSTANCE_TOOLS: [&str; 5] = ["apt", "dnf", "cargo", "nix", "script"];
+
+// Extra named instance of a pacman, flatpak, pipx or npm installer, packages
+// installed through it are tracked as tool@name:
+//
+// [[installers]]
+// tool = "pacman"
+// name = "chroot"
+// root = "/mnt/chroot"
+// dbpath = "/mnt/chroot/var/lib/pacman"
+// removal = "package"
+#[derive(Deserialize, Debug)]
+#[serde(tag = "tool", rename_all = "lowercase")]
+pub enum InstallerInstance {
+    Pacman {
+        name: String,
+        #[serde(flatten)]
+        options: PacmanOptions,
+    },
+    Flatpak {
+        name: String,
+        installation: FlatpakInstallation,
+    },
+    Pipx {
+        name: String,
+        home: Option<String>,
+        bin_dir: Option<String>,
+    },
+    Npm {
+        name: String,
+        prefix: Option<String>,
+    },
+}
+
+impl Config {
+    pub fn load() -> Result<Self, String> {
+        let config_path = PathBuf::from(shellexpand::tilde(CONFIG_PATH).to_string());
+
+        if !config_path.exists() {
+            return Ok(Config::default());
+        }
+
+        let content = fs::read_to_string(&config_path)
+            .map_err(|e| format!("Could not read config file {}: {e}", config_path.display()))?;
+
+        let invalid = |e: String| format!("Invalid config file {}: {e}", config_path.display());
+        let table: toml::Table = toml::from_str(&content).map_err(|e| invalid(e.to_string()))?;
+        check_instance_tools(&table).map_err(invalid)?;
+
+        toml::from_str(&content).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn set_interactive(&mut self) {
+        self.pacman.interactive = true;
+
+        for instance in &mut self.installers {
+            if let InstallerInstance::Pacman { options, .. } = instance {
+                options.interactive = true;
+            }
+        }
+    }
+}
+
+// Serde would report these tools as unknown
+fn check_instance_tools(table: &toml::Table) -> Result<(), String> {
+    let instances = table
+        .get("installers")
+        .and_then(toml::Value::as_array)
+        .into_iter()
+        .flatten();
+
+    for instance in instances {
+        if let Some(tool) = instance.get("tool").and_then(toml::Value::as_str)
+            && SINGLE_INSTANCE_TOOLS.contains(&tool)
+        {
+            return Err(format!(
+                "{tool} installers can't have named instances, only pacman, flatpak, pipx and npm can"
+            ));
+        }
+    }
+
+    Ok(())
+}
@@ -1,17 +1,50 @@
+use apt_bindings::AptInstaller;
+use cargo_bindings::CargoInstaller;
 use clap::{Args, Parser, Subcommand};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
 
 // Assuming these are your existing imports
+use config::{Config, InstallerInstance, StoreFormat};
+use dnf_bindings::DnfInstaller;
 use err::Result;
-use models::InstallationTools;
-use pacman_bindings::PermissionMethod;
-use pazckager_core::{models::PazckagerCoreBuilder, *};
+use flatpak_bindings::FlatpakInstaller;
+use models::{
+    InstallationTools, InstallerId, OperationReport, OperationStatus, PackageData, PackageMetadata,
+};
+use nix_bindings::NixInstaller;
+use npm_bindings::NpmInstaller;
+use pacman_bindings::graph::DependencyGraph;
+use pacman_bindings::{PROVIDER_KEY, PacmanInstaller};
+use pazckager_core::cancellation::interrupt_token;
+use pazckager_core::plugin::{PluginInstaller, discover_plugins};
+use pazckager_core::{models::PazckagerCoreBuilder, traits::PazckagerStorage, *};
 use pazckager_json_storage::JsonPazckagerStorage;
+use pazckager_sqlite_storage::SqlitePazckagerStorage;
+use pazckager_toml_storage::TomlPazckagerStorage;
+use pipx_bindings::PipxInstaller;
+use script_bindings::ScriptInstaller;
+
+mod config;
 
 // CLI structure definition
 #[derive(Parser)]
 #[command(name = "pazckager")]
 #[command(about = "Package management tool", long_about = None)]
 struct Cli {
+    /// Store format to use, overrides the store_format config setting
+    #[arg(long, global = true)]
+    store_format: Option<StoreFormat>,
+    /// Store file to use, overrides the store_path config setting
+    #[arg(long, global = true)]
+    store_path: Option<String>,
+    /// Lets pacman ask its questions, like which provider to install, on the terminal
+    #[arg(long, global = true)]
+    interactive: bool,
+    /// Seconds a single tool command may run, overrides the operation_timeout config setting
+    #[arg(long, global = true)]
+    timeout: Option<u64>,
     #[command(subcommand)]
     command: Commands,
 }
@@ -48,6 +81,28 @@ enum Commands {
     ChangePackageCategory(ChangePackageCategoryArgs),
     // Get the info of a category
     GetCategory(GetCategoryArgs),
+    /// Import an existing json store into a sqlite store
+    MigrateJsonStore(MigrateJsonStoreArgs),
+    /// Lists the installation tools with their status and capabilities
+    Tools,
+    /// Shows what the store and the installation tool know about a package
+    Info(InfoArgs),
+    /// Shows the packages and disk usage of every category
+    Stats(StatsArgs),
+    /// Explains which tracked packages need a pacman package
+    Why(WhyArgs),
+    /// Shows the pacman package owning a file and the category that brought it in
+    Owns(OwnsArgs),
+}
+
+#[derive(Args)]
+struct MigrateJsonStoreArgs {
+    /// Path of the json store to import
+    #[arg(short, long, default_value = "~/.local/share/pazckager_store.json")]
+    json_path: String,
+    /// Path of the sqlite store to import into
+    #[arg(short, long, default_value = "~/.local/share/pazckager_store.db")]
+    sqlite_path: String,
 }
 
 #[derive(Args)]
@@ -62,6 +117,9 @@ struct InstallPackageArgs {
     /// Name of the package to add
     #[arg(short, long)]
     package_name: String,
+    /// Installer of the package as tool or tool@instance, needed when the name is tracked for several installers
+    #[arg(short, long)]
+    tool: Option<InstallerId>,
 }
 
 #[derive(Args)]
@@ -69,12 +127,23 @@ struct AddPackageArgs {
     /// Name of the package to add
     #[arg(short, long)]
     package_name: String,
-    /// Installation tool to use, (pacman)
+    /// Installer to use as tool or tool@instance, (pacman, pacman@chroot)
     #[arg(short, long)]
-    tool: InstallationTools,
+    tool: InstallerId,
     /// Category for the package (optional)
     #[arg(short, long)]
     category: Option<String>,
+    /// Tool specific package option as key=value, like remote=flathub (repeatable)
+    #[arg(short, long = "meta", value_parser = parse_metadata)]
+    metadata: Vec<(String, String)>,
+}
+
+fn parse_metadata(value: &str) -> std::result::Result<(String, String), String> {
+    let (key, value) = value
+        .split_once('=')
+        .ok_or(format!("Invalid metadata {value}, expected key=value"))?;
+
+    Ok((key.to_string(), value.to_string()))
 }
 
 #[derive(Args)]
@@ -82,6 +151,9 @@ struct DeletePackageArgs {
     /// Name of the package to delete
     #[arg(short, long)]
     package_name: String,
+    /// Installer of the package as tool or tool@instance, needed when the name is tracked for several installers
+    #[arg(short, long)]
+    tool: Option<InstallerId>,
 }
 
 #[derive(Args)]
@@ -89,6 +161,43 @@ struct UpdatePackageArgs {
     /// Name of the package to update
     #[arg(short, long)]
     package_name: String,
+    /// Installer of the package as tool or tool@instance, needed when the name is tracked for several installers
+    #[arg(short, long)]
+    tool: Option<InstallerId>,
+}
+
+#[derive(Args)]
+struct InfoArgs {
+    /// Name of the package to describe
+    package_name: String,
+    /// Installer of the package as tool or tool@instance, needed when the name is tracked for several installers
+    #[arg(short, long)]
+    tool: Option<InstallerId>,
+}
+
+#[derive(Args)]
+struct StatsArgs {
+    /// Prints the statistics as JSON
+    #[arg(long)]
+    json: bool,
+}
+
+#[derive(Args)]
+struct WhyArgs {
+    /// Name of the installed pacman package
+    package_name: String,
+    /// Pacman instance to look in, the default one when it is not set
+    #[arg(short, long)]
+    instance: Option<String>,
+}
+
+#[derive(Args)]
+struct OwnsArgs {
+    /// File or directory to look up
+    path: PathBuf,
+    /// Pacman instance to look in, the default one when it is not set
+    #[arg(short, long)]
+    instance: Option<String>,
 }
 
 #[derive(Args)]
@@ -137,30 +246,200 @@ struct ChangePackageCategoryArgs {
     /// New category name
     #[arg(short, long)]
     new_category: String,
+    /// Installer of the package as tool or tool@instance, needed when the name is tracked for several installers
+    #[arg(short, long)]
+    tool: Option<InstallerId>,
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    let store = JsonPazckagerStorage::new("~/.local/share/pazckager_store.json").unwrap();
-    let pacman = pacman_bindings::PacmanInstaller::new(PermissionMethod::Sudo);
+    if let Commands::MigrateJsonStore(args) = &cli.command {
+        let json_store = JsonPazckagerStorage::open(&args.json_path)?;
+        let mut sqlite_store = SqlitePazckagerStorage::new(&args.sqlite_path)?;
+        sqlite_store.import_from(&json_store)?;
+        println!("Json store migrated successfully to {}", args.sqlite_path);
+        return Ok(());
+    }
+
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    if cli.interactive {
+        config.set_interactive();
+    }
+    if cli.timeout.is_some() {
+        config.operation_timeout = cli.timeout;
+    }
+
+    let store_format = cli.store_format.unwrap_or(config.store_format);
+    let store_path = cli
+        .store_path
+        .or(config.store_path.clone())
+        .unwrap_or(store_format.default_path().to_string());
 
-    let mut core = PazckagerCoreBuilder::new(store)
+    match store_format {
+        StoreFormat::Json => run(
+            JsonPazckagerStorage::new(&store_path)?,
+            cli.command,
+            &config,
+        ),
+        StoreFormat::Toml => run(
+            TomlPazckagerStorage::new(&store_path)?,
+            cli.command,
+            &config,
+        ),
+        StoreFormat::Sqlite => run(
+            SqlitePazckagerStorage::new(&store_path)?,
+            cli.command,
+            &config,
+        ),
+    }
+}
+
+fn pipx_installer(home: &Option<String>, bin_dir: &Option<String>) -> PipxInstaller {
+    let mut pipx = PipxInstaller::new();
+    if let Some(home) = home {
+        pipx = pipx.with_home(shellexpand::tilde(home).to_string());
+    }
+    if let Some(bin_dir) = bin_dir {
+        pipx = pipx.with_bin_dir(shellexpand::tilde(bin_dir).to_string());
+    }
+    pipx
+}
+
+fn npm_installer(prefix: &Option<String>) -> NpmInstaller {
+    let mut npm = NpmInstaller::new();
+    if let Some(prefix) = prefix {
+        npm = npm.with_prefix(shellexpand::tilde(prefix).to_string());
+    }
+    npm
+}
+
+// Pacman of the default instance or of the named one, None when there is no
+// instance with that name
+fn pacman_installer(config: &Config, instance: Option<&str>) -> Option<PacmanInstaller> {
+    let options = match instance {
+        None => config.pacman.clone(),
+        Some(instance) => config
+            .installers
+            .iter()
+            .find_map(|installer| match installer {
+                InstallerInstance::Pacman { name, options } if name == instance => {
+                    Some(options.clone())
+                }
+                _ => None,
+            })?,
+    };
+
+    Some(PacmanInstaller::new(config.permission_method).with_options(options))
+}
+
+// Categories of the tracked packages of a pacman instance, by the name pacman
+// installed them under
+fn tracked_pacman_packages(
+    packages: Vec<PackageData>,
+    instance: Option<&str>,
+) -> HashMap<String, String> {
+    packages
+        .into_iter()
+        .filter(|package| {
+            package.installation_tool == InstallationTools::Pacman
+                && package.instance.as_deref() == instance
+        })
+        .map(|package| {
+            let installed_name = package
+                .metadata
+                .get(PROVIDER_KEY)
+                .cloned()
+                .unwrap_or(package.package_name);
+
+            (installed_name, package.category_name)
+        })
+        .collect()
+}
+
+fn run<T: PazckagerStorage>(store: T, command: Commands, config: &Config) -> Result<()> {
+    let pacman = PacmanInstaller::new(config.permission_method).with_options(config.pacman.clone());
+    let apt = AptInstaller::new(config.permission_method);
+    let dnf = DnfInstaller::new(config.permission_method);
+    let flatpak = FlatpakInstaller::new(config.flatpak_installation);
+    let cargo = CargoInstaller::new();
+
+    let pipx = pipx_installer(&config.pipx_home, &config.pipx_bin_dir);
+    let npm = npm_installer(&config.npm_prefix);
+
+    let mut builder = PazckagerCoreBuilder::new(store)
         .with_installer(pacman)
-        .build()
-        .unwrap();
+        .with_installer(apt)
+        .with_installer(dnf)
+        .with_installer(flatpak)
+        .with_installer(cargo)
+        .with_installer(pipx)
+        .with_installer(npm)
+        .with_installer(NixInstaller::new());
+
+    for instance in &config.installers {
+        builder = match instance {
+            InstallerInstance::Pacman { name, options } => {
+                let pacman =
+                    PacmanInstaller::new(config.permission_method).with_options(options.clone());
+                builder.with_named_installer(name, pacman)
+            }
+            InstallerInstance::Flatpak { name, installation } => {
+                builder.with_named_installer(name, FlatpakInstaller::new(*installation))
+            }
+            InstallerInstance::Pipx {
+                name,
+                home,
+                bin_dir,
+            } => builder.with_named_installer(name, pipx_installer(home, bin_dir)),
+            InstallerInstance::Npm { name, prefix } => {
+                builder.with_named_installer(name, npm_installer(prefix))
+            }
+        };
+    }
+
+    for script_config in &config.script_installers {
+        match ScriptInstaller::new(script_config.clone()) {
+            Ok(script_installer) => builder = builder.with_installer(script_installer),
+            Err(err) => eprintln!("Skipping script installer: {err}"),
+        }
+    }
+
+    for plugin_name in discover_plugins() {
+        builder = builder.with_installer(PluginInstaller::new(&plugin_name));
+    }
+
+    // Ctrl-C stops the running tool and ends the operation, leaving the store
+    // consistent, instead of killing pazckager halfway through
+    builder = builder.with_cancellation(interrupt_token());
+    if let Some(seconds) = config.operation_timeout {
+        builder = builder.with_timeout(Duration::from_secs(seconds));
+    }
+
+    let mut core = builder.build().unwrap();
 
-    match cli.command {
+    match command {
         Commands::AddPackage(args) => {
-            core.add_package(args.package_name, args.tool, args.category)?;
+            core.add_package(
+                args.package_name,
+                args.tool,
+                args.category,
+                args.metadata.into_iter().collect::<PackageMetadata>(),
+            )?;
             println!("Package added successfully");
         }
         Commands::DeletePackage(args) => {
-            core.delete_package(args.package_name)?;
+            core.delete_package(args.package_name, args.tool)?;
             println!("Package deleted successfully");
         }
         Commands::UpdatePackage(args) => {
-            core.update_package(args.package_name)?;
+            core.update_package(args.package_name, args.tool)?;
             println!("Package updated successfully");
         }
         Commands::ListPackages => {
@@ -168,12 +447,15 @@ fn main() -> Result<()> {
             println!("Packages:");
             for package in packages {
                 println!(
-                    "- {} (Tool: {:?}, Category: {}, installed: {})",
+                    "- {} (Tool: {}, Category: {}, installed: {})",
                     package.package_name,
-                    package.installation_tool,
+                    package.installer(),
                     package.category_name,
                     package.installed
                 );
+                for (key, value) in package.metadata {
+                    println!("  {key}: {value}");
+                }
             }
         }
         Commands::ListCategories => {
@@ -191,8 +473,10 @@ fn main() -> Result<()> {
             println!("Packages in category:");
             for package in packages {
                 println!(
-                    "- {} (Tool: {:?}, installed: {})",
-                    package.package_name, package.installation_tool, package.installed
+                    "- {} (Tool: {}, installed: {})",
+                    package.package_name,
+                    package.installer(),
+                    package.installed
                 );
             }
         }
@@ -202,35 +486,39 @@ fn main() -> Result<()> {
             println!("Packages succesfully sync");
         }
         Commands::InstallPackage(args) => {
-            if let Err(err) = core.install_package(args.package_name) {
-                println!("Error installing package: {err}");
+            let result = core.install_package(args.package_name, args.tool);
+            if matches!(&result, Err(err) if err.is_cancelled()) {
+                println!("Package install cancelled");
             }
-            println!("Package uninstalled succesfully");
+            result?;
+            println!("Package installed successfully");
         }
         Commands::UninstallPackage(args) => {
-            if let Err(err) = core.uninstall_package(args.package_name) {
-                println!("Error installing package: {err}");
+            let result = core.uninstall_package(args.package_name, args.tool);
+            if matches!(&result, Err(err) if err.is_cancelled()) {
+                println!("Package uninstall cancelled");
             }
-            println!("Package uninstalled succesfully");
+            result?;
+            println!("Package uninstalled successfully");
         }
         Commands::InstallCategory(args) => {
-            core.install_category(args.category_name)?;
-            println!("Category installed successfully");
+            let report = core.install_category(args.category_name)?;
+            print_report("install", &report);
         }
         Commands::AddCategory(args) => {
             core.add_category(args.category_name, args.additional_info)?;
             println!("Category added successfully");
         }
         Commands::UninstallCategory(args) => {
-            core.uninstall_category(args.category_name)?;
-            println!("Category uninstalled successfully");
+            let report = core.uninstall_category(args.category_name)?;
+            print_report("uninstall", &report);
         }
         Commands::DeleteCategory(args) => {
             core.delete_category(args.category_name)?;
             println!("Category deleted successfully");
         }
         Commands::ChangePackageCategory(args) => {
-            core.change_package_category(args.new_category, args.package_name)?;
+            core.change_package_category(args.new_category, args.package_name, args.tool)?;
             println!("Package category changed successfully");
         }
         Commands::GetCategory(args) => {
@@ -241,11 +529,201 @@ fn main() -> Result<()> {
                 category.additional_info.unwrap_or(String::new())
             );
         }
+        Commands::Tools => {
+            println!("Installation tools:");
+            for tool in core.tools() {
+                let status = match (tool.available, tool.version) {
+                    (false, _) => "not available".to_string(),
+                    (true, Some(version)) => format!("available, {version}"),
+                    (true, None) => "available".to_string(),
+                };
+                println!("- {} ({status})", tool.installer);
+
+                if !tool.capabilities.is_empty() {
+                    let capabilities = tool
+                        .capabilities
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>();
+                    println!("  Capabilities: {}", capabilities.join(", "));
+                }
+            }
+        }
+        Commands::Info(args) => {
+            let details = core.package_info(args.package_name, args.tool)?;
+            let package = details.package;
+
+            println!("Package: {}", package.package_name);
+            println!("Tool: {}", package.installer());
+            println!("Installed: {}", package.installed);
+            println!("Category: {}", package.category_name);
+            if let Some(notes) = core.get_category(package.category_name)?.additional_info {
+                println!("Category notes: {notes}");
+            }
+            if !package.metadata.is_empty() {
+                println!("Pins and settings:");
+                for (key, value) in package.metadata {
+                    println!("  {key}: {value}");
+                }
+            }
+
+            let Some(info) = details.info else {
+                println!("The installation tool has no information about it");
+                return Ok(());
+            };
+
+            for (label, value) in [
+                ("Description", info.description),
+                ("Version", info.version),
+                ("Repository", info.repository),
+                ("URL", info.url),
+                ("Installed size", info.installed_size),
+                ("Install date", info.install_date),
+            ] {
+                if let Some(value) = value {
+                    println!("{label}: {value}");
+                }
+            }
+            for (label, values) in [
+                ("Licenses", info.licenses),
+                ("Depends on", info.dependencies),
+                ("Required by", info.required_by),
+            ] {
+                if !values.is_empty() {
+                    println!("{label}: {}", values.join(", "));
+                }
+            }
+        }
+        Commands::Stats(args) => {
+            let stats = core.category_stats()?;
+
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&stats).unwrap());
+                return Ok(());
+            }
+
+            println!("Categories:");
+            for category in stats {
+                println!(
+                    "- {} ({} packages, {} installed)",
+                    category.category_name, category.packages, category.installed
+                );
+                println!(
+                    "  Installed size: {}, exclusive: {}",
+                    format_size(category.installed_size),
+                    format_size(category.exclusive_size)
+                );
+            }
+        }
+        Commands::Why(args) => {
+            let instance = args.instance.as_deref();
+            let Some(pacman) = pacman_installer(config, instance) else {
+                println!(
+                    "There is no pacman instance named {}",
+                    args.instance.unwrap()
+                );
+                return Ok(());
+            };
+
+            let graph = pacman.dependency_graph()?;
+            let tracked = tracked_pacman_packages(core.get_packages()?, instance);
+
+            if graph.package(&args.package_name).is_none() {
+                println!("{} is not installed", args.package_name);
+                return Ok(());
+            }
+
+            print_why(&graph, &tracked, &args.package_name);
+        }
+        Commands::Owns(args) => {
+            let instance = args.instance.as_deref();
+            let Some(pacman) = pacman_installer(config, instance) else {
+                println!(
+                    "There is no pacman instance named {}",
+                    args.instance.unwrap()
+                );
+                return Ok(());
+            };
+
+            let path = std::path::absolute(&args.path).unwrap_or(args.path);
+            let owners = pacman.file_owners(&path)?;
+            if owners.is_empty() {
+                println!("No package owns {}", path.display());
+                return Ok(());
+            }
+
+            let graph = pacman.dependency_graph()?;
+            let tracked = tracked_pacman_packages(core.get_packages()?, instance);
+
+            for owner in owners {
+                println!("{} is owned by {owner}", path.display());
+                print_why(&graph, &tracked, &owner);
+            }
+        }
+        Commands::MigrateJsonStore(_) => unreachable!("handled before building the core"),
     }
 
     Ok(())
 }
 
+fn describe_package(tracked: &HashMap<String, String>, name: &str) -> String {
+    match tracked.get(name) {
+        Some(category_name) => format!("{name} (category: {category_name})"),
+        None => name.to_string(),
+    }
+}
+
+// Prints the chains of tracked packages requiring the package
+fn print_why(graph: &DependencyGraph, tracked: &HashMap<String, String>, package_name: &str) {
+    if tracked.contains_key(package_name) {
+        println!("{} is tracked", describe_package(tracked, package_name));
+    }
+
+    let chains = graph.why(package_name, |name| tracked.contains_key(name));
+    if chains.is_empty() && !tracked.contains_key(package_name) {
+        println!("Nothing tracked requires {package_name}");
+    }
+    for chain in chains {
+        let chain: Vec<String> = chain
+            .into_iter()
+            .map(|name| describe_package(tracked, name))
+            .collect();
+        println!("{}", chain.join(" ← "));
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    let mut size = bytes as f64;
+
+    for unit in ["B", "KiB", "MiB", "GiB"] {
+        if size < 1024.0 {
+            return format!("{size:.1} {unit}");
+        }
+        size /= 1024.0;
+    }
+
+    format!("{size:.1} TiB")
+}
+
+fn print_report(operation: &str, report: &OperationReport) {
+    match report.status {
+        OperationStatus::Completed => {
+            println!("Category {operation} {}", report.status);
+        }
+        OperationStatus::Cancelled => {
+            println!(
+                "Category {operation} {}, {} packages done, {} left:",
+                report.status,
+                report.completed.len(),
+                report.remaining.len()
+            );
+            for package in &report.remaining {
+                println!("- {package}");
+            }
+        }
+    }
+}
+
 // Add this to your Cargo.toml dependencies:
 // [dependencies]
 // clap = { version = "4.0", features = ["derive"] }
@@ -0,0 +1,64 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+// Asks running operations to stop, clones share the same flag so the token
+// can be cancelled from another thread or a signal handler
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+static INTERRUPT_TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    if let Some(token) = INTERRUPT_TOKEN.get() {
+        token.cancel();
+    }
+}
+
+// Token cancelled by SIGINT. Once it is requested Ctrl-C no longer kills the
+// process, running tools are stopped and the operation ends after leaving the
+// store consistent
+pub fn interrupt_token() -> CancellationToken {
+    let token = INTERRUPT_TOKEN.get_or_init(CancellationToken::new).clone();
+
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as *const () as libc::sighandler_t,
+        );
+    }
+
+    token
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_cancellation() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+
+        assert!(!clone.is_cancelled());
+
+        token.cancel();
+
+        assert!(clone.is_cancelled());
+    }
+}
@@ -1,5 +1,6 @@
 use thiserror::Error;
 
+use crate::models::InstallerId;
 use crate::traits::{StoreError, ToolError};
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -14,8 +15,31 @@ pub enum Error {
     PackageAlreadyExists,
     #[error("Package does not exists")]
     PackageDoesNotExists,
+    #[error(
+        "Package {0} is tracked for several installers ({installers}), pick one of them",
+        installers = join_installers(.1)
+    )]
+    AmbiguousPackage(String, Vec<InstallerId>),
     #[error("Installation tool does not exist")]
     InstallationToolDoesNotExist,
+    #[error("Installation tool {0} is not available on this system")]
+    InstallationToolUnavailable(InstallerId),
+    #[error("Could not list the {0} packages, nothing was synced: {1}")]
+    ListingPackages(InstallerId, ToolError),
     #[error("Category does not exists")]
     CategoryDoesNotExist,
 }
+
+impl Error {
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Error::Installer(ToolError::Cancelled(_)))
+    }
+}
+
+fn join_installers(installers: &[InstallerId]) -> String {
+    installers
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
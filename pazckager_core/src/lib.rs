@@ -1,23 +1,38 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
-use models::{Category, InstallationTools, PackageData, RawPackageData};
-use traits::{InstallationTool, PazckagerStorage};
+use cancellation::CancellationToken;
+use models::{
+    Category, CategoryStats, InstallerId, OperationReport, OperationStatus, PackageData,
+    PackageDetails, PackageKey, PackageMetadata, PackageUsage,
+};
+use program::RunControl;
+use traits::{InstallationTool, PazckagerStorage, StoreError, ToolError, ToolStatus};
 
+pub mod cancellation;
 pub mod err;
 pub mod models;
+pub mod permissions;
+pub mod plugin;
+pub mod program;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
 pub mod traits;
 
 use err::{Error, Result};
 
 pub struct PazckagerCore<T: PazckagerStorage> {
     store: T,
-    package_installers: HashMap<InstallationTools, Box<dyn InstallationTool>>,
+    package_installers: HashMap<InstallerId, Box<dyn InstallationTool>>,
+    unavailable_installers: HashMap<InstallerId, ToolStatus>,
+    run_control: RunControl,
 }
 
 impl<T: PazckagerStorage> PazckagerCore<T> {
     fn new(
         mut store: T,
-        package_installers: HashMap<InstallationTools, Box<dyn InstallationTool>>,
+        package_installers: HashMap<InstallerId, Box<dyn InstallationTool>>,
+        unavailable_installers: HashMap<InstallerId, ToolStatus>,
+        run_control: RunControl,
     ) -> Result<Self> {
         if !store.category_exists("default")? {
             store.store_category(Category {
@@ -29,29 +44,83 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
         Ok(Self {
             store,
             package_installers,
+            unavailable_installers,
+            run_control,
         })
     }
 
+    // Finds a tracked package by name, the installer is only needed when the
+    // same name is tracked for more than one installer
+    fn resolve_package(
+        &self,
+        package_name: &str,
+        installer: Option<InstallerId>,
+    ) -> Result<PackageData> {
+        if let Some(installer) = installer {
+            return match self
+                .store
+                .get_package(&PackageKey::new(installer, package_name))
+            {
+                Err(StoreError::NotFound(_)) => Err(Error::PackageDoesNotExists),
+                result => Ok(result?),
+            };
+        }
+
+        let mut packages = self.store.get_packages_by_name(package_name)?;
+
+        match packages.len() {
+            0 => Err(Error::PackageDoesNotExists),
+            1 => Ok(packages.remove(0)),
+            _ => Err(Error::AmbiguousPackage(
+                package_name.to_string(),
+                packages
+                    .into_iter()
+                    .map(|package| package.installer())
+                    .collect(),
+            )),
+        }
+    }
+
+    fn installer_mut(&mut self, installer: &InstallerId) -> Result<&mut Box<dyn InstallationTool>> {
+        if self.unavailable_installers.contains_key(installer) {
+            return Err(Error::InstallationToolUnavailable(installer.clone()));
+        }
+
+        self.package_installers
+            .get_mut(installer)
+            .ok_or(Error::InstallationToolDoesNotExist)
+    }
+
+    // Status of every registered installer, including the skipped ones
+    pub fn tools(&self) -> Vec<ToolStatus> {
+        let mut tools: Vec<ToolStatus> = self
+            .package_installers
+            .iter()
+            .map(|(installer, package_installer)| {
+                ToolStatus::of(installer.clone(), package_installer.as_ref())
+            })
+            .chain(self.unavailable_installers.values().cloned())
+            .collect();
+
+        tools.sort_by_key(|tool| tool.installer.to_string());
+        tools
+    }
+
     pub fn change_package_category(
         &mut self,
         category_name: String,
         package_name: String,
+        installer: Option<InstallerId>,
     ) -> Result<()> {
         if !self.store.category_exists(&category_name)? {
             return Err(Error::CategoryDoesNotExist);
         }
 
-        if !self.store.package_exists(&package_name)? {
-            return Err(Error::PackageDoesNotExists);
-        }
-
-        let package = self.store.get_package(&package_name)?;
+        let package = self.resolve_package(&package_name, installer)?;
 
         self.store.update_package(PackageData {
-            package_name: package.package_name,
-            installation_tool: package.installation_tool,
             category_name,
-            installed: package.installed,
+            ..package
         })?;
 
         Ok(())
@@ -67,16 +136,128 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
         Ok(category)
     }
 
-    pub fn install_category(&mut self, category_name: String) -> Result<()> {
-        let packages = self.store.get_packages()?;
+    // Runs an operation on several packages inside a batch of every installer
+    // involved, batches are ended even when the operation fails
+    fn in_batch<R>(
+        &mut self,
+        packages: &[PackageData],
+        operation: impl FnOnce(&mut Self) -> Result<R>,
+    ) -> Result<R> {
+        let installers: BTreeSet<InstallerId> =
+            packages.iter().map(PackageData::installer).collect();
+        let mut started = Vec::new();
 
-        for package in packages {
-            if !package.installed && package.category_name == category_name {
-                self.install_package(package.package_name)?;
+        let mut begun = Ok(());
+        for installer in installers {
+            if let Some(package_installer) = self.package_installers.get_mut(&installer) {
+                begun = package_installer.begin_batch().map_err(Error::from);
+                if begun.is_err() {
+                    break;
+                }
+                started.push(installer);
             }
         }
 
-        Ok(())
+        let mut result = begun.and_then(|_| operation(self));
+
+        for installer in started {
+            if let Some(package_installer) = self.package_installers.get_mut(&installer) {
+                let ended = package_installer.end_batch().map_err(Error::from);
+                result = result.and_then(|value| ended.map(|_| value));
+            }
+        }
+
+        result
+    }
+
+    // Runs the operation on every package until one fails. A cancellation is
+    // not a failure, it ends the operation with the rest of the packages left
+    // as they were
+    fn for_each_package(
+        &mut self,
+        packages: &[PackageData],
+        mut operation: impl FnMut(&mut Self, &PackageData) -> Result<()>,
+    ) -> Result<OperationReport> {
+        let mut report = OperationReport::default();
+
+        for (index, package) in packages.iter().enumerate() {
+            let result = if self.run_control.cancellation.is_cancelled() {
+                Err(Error::Installer(ToolError::Cancelled(
+                    package.key().to_string(),
+                )))
+            } else {
+                operation(self, package)
+            };
+
+            match result {
+                Ok(()) => report.completed.push(package.key()),
+                Err(error) if error.is_cancelled() => {
+                    report.status = OperationStatus::Cancelled;
+                    report.remaining = packages[index..].iter().map(PackageData::key).collect();
+                    break;
+                }
+                Err(error) => return Err(error),
+            }
+        }
+
+        Ok(report)
+    }
+
+    // A tool stopped halfway may still have changed the system, the installed
+    // flag of the package is taken from what the tool lists afterwards. The
+    // token is already cancelled, the listing gets a fresh one and is still
+    // bounded by the timeout
+    fn interrupted(&mut self, mut package: PackageData, error: ToolError) -> Error {
+        if !matches!(error, ToolError::Cancelled(_) | ToolError::TimedOut(..)) {
+            return error.into();
+        }
+
+        let recheck_control = RunControl {
+            cancellation: CancellationToken::new(),
+            timeout: self.run_control.timeout,
+        };
+        let installed = self
+            .package_installers
+            .get_mut(&package.installer())
+            .and_then(|package_installer| {
+                package_installer.set_run_control(recheck_control);
+                let listed = package_installer.get_packages();
+                package_installer.set_run_control(self.run_control.clone());
+
+                let installed_name = package_installer.installed_name(&package);
+
+                Some(
+                    listed
+                        .ok()?
+                        .iter()
+                        .any(|raw_data| raw_data.package_name == installed_name),
+                )
+            });
+
+        if let Some(installed) = installed {
+            package.installed = installed;
+
+            if let Err(store_error) = self.store.update_package(package) {
+                return store_error.into();
+            }
+        }
+
+        error.into()
+    }
+
+    pub fn install_category(&mut self, category_name: String) -> Result<OperationReport> {
+        let packages: Vec<PackageData> = self
+            .store
+            .get_packages()?
+            .into_iter()
+            .filter(|package| !package.installed && package.category_name == category_name)
+            .collect();
+
+        self.in_batch(&packages, |core| {
+            core.for_each_package(&packages, |core, package| {
+                core.install_package(package.package_name.clone(), Some(package.installer()))
+            })
+        })
     }
 
     pub fn add_category(
@@ -92,16 +273,19 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
         Ok(())
     }
 
-    pub fn uninstall_category(&mut self, category_name: String) -> Result<()> {
-        let packages = self.store.get_packages()?;
+    pub fn uninstall_category(&mut self, category_name: String) -> Result<OperationReport> {
+        let packages: Vec<PackageData> = self
+            .store
+            .get_packages()?
+            .into_iter()
+            .filter(|package| package.installed && package.category_name == category_name)
+            .collect();
 
-        for package in packages {
-            if package.installed && package.category_name == category_name {
-                self.uninstall_package(package.package_name)?;
-            }
-        }
-
-        Ok(())
+        self.in_batch(&packages, |core| {
+            core.for_each_package(&packages, |core, package| {
+                core.uninstall_package(package.package_name.clone(), Some(package.installer()))
+            })
+        })
     }
 
     pub fn delete_category(&mut self, category_name: String) -> Result<()> {
@@ -110,10 +294,8 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
         for package in packages {
             if package.category_name == category_name {
                 self.store.update_package(PackageData {
-                    package_name: package.package_name,
-                    installation_tool: package.installation_tool,
                     category_name: "default".to_string(),
-                    installed: package.installed,
+                    ..package
                 })?;
             }
         }
@@ -123,16 +305,25 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
         Ok(())
     }
 
-    pub fn install_package(&mut self, package_name: String) -> Result<()> {
-        let mut package_data = self.store.get_package(&package_name)?;
+    pub fn install_package(
+        &mut self,
+        package_name: String,
+        installer: Option<InstallerId>,
+    ) -> Result<()> {
+        let mut package_data = self.resolve_package(&package_name, installer)?;
 
-        let installation_tool = self
-            .package_installers
-            .get_mut(&package_data.installation_tool)
-            .ok_or(Error::PackageDoesNotExists)?;
+        let installer = package_data.installer();
 
-        installation_tool.install_package(&package_name)?;
+        if let Err(error) = self
+            .installer_mut(&installer)?
+            .install_package(&package_data)
+        {
+            return Err(self.interrupted(package_data, error));
+        }
 
+        package_data.metadata = self
+            .installer_mut(&installer)?
+            .installed_metadata(&package_data);
         package_data.installed = true;
 
         self.store.update_package(package_data)?;
@@ -143,13 +334,11 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
     pub fn add_package(
         &mut self,
         package_name: String,
-        installation_tool: InstallationTools,
+        installer: InstallerId,
         package_category_name: Option<String>,
+        metadata: PackageMetadata,
     ) -> Result<()> {
-        let package_installer = self
-            .package_installers
-            .get_mut(&installation_tool)
-            .ok_or(Error::InstallationToolDoesNotExist)?;
+        let installation_tool = self.installer_mut(&installer)?.get_type();
 
         let mut category_name = package_category_name
             .clone()
@@ -165,91 +354,100 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
             return Err(Error::CategoryDoesNotExist);
         }
 
-        if self.store.package_exists(&package_name)? {
-            return Err(Error::PackageAlreadyExists);
-        }
-
-        self.store.store_package(PackageData {
+        let package_data = PackageData {
             package_name,
-            installation_tool: package_installer.get_type(),
+            installation_tool,
+            instance: installer.instance,
             category_name,
             installed: false,
-        })?;
-
-        Ok(())
-    }
+            metadata,
+        };
 
-    pub fn delete_package(&mut self, package_name: String) -> Result<()> {
-        if !self.store.package_exists(&package_name)? {
-            return Err(Error::PackageDoesNotExists);
+        if self.store.package_exists(&package_data.key())? {
+            return Err(Error::PackageAlreadyExists);
         }
 
-        let package = self.store.get_package(&package_name)?;
-
-        let package_installer = self
-            .package_installers
-            .get_mut(&package.installation_tool)
-            .ok_or(Error::InstallationToolDoesNotExist)?;
-
-        package_installer.delete_package(&package.package_name)?;
-        self.store.remove_package(&package_name)?;
+        self.store.store_package(package_data)?;
 
         Ok(())
     }
 
-    pub fn uninstall_package(&mut self, package_name: String) -> Result<()> {
-        if !self.store.package_exists(&package_name)? {
-            return Err(Error::PackageDoesNotExists);
-        }
-
-        let package = self.store.get_package(&package_name)?;
-
-        let package_installer = self
-            .package_installers
-            .get_mut(&package.installation_tool)
-            .ok_or(Error::InstallationToolDoesNotExist)?;
+    pub fn delete_package(
+        &mut self,
+        package_name: String,
+        installer: Option<InstallerId>,
+    ) -> Result<()> {
+        let package = self.resolve_package(&package_name, installer)?;
 
-        package_installer.delete_package(&package.package_name)?;
+        if let Err(error) = self
+            .installer_mut(&package.installer())?
+            .delete_package(&package)
+        {
+            return Err(self.interrupted(package, error));
+        }
+        self.store.remove_package(&package.key())?;
 
         Ok(())
     }
 
-    pub fn update_package(&mut self, package_name: String) -> Result<()> {
-        if !self.store.package_exists(&package_name)? {
-            return Err(Error::PackageDoesNotExists);
+    pub fn uninstall_package(
+        &mut self,
+        package_name: String,
+        installer: Option<InstallerId>,
+    ) -> Result<()> {
+        let mut package = self.resolve_package(&package_name, installer)?;
+
+        if let Err(error) = self
+            .installer_mut(&package.installer())?
+            .delete_package(&package)
+        {
+            return Err(self.interrupted(package, error));
         }
 
-        let package = self.store.get_package(&package_name)?;
+        package.installed = false;
+        self.store.update_package(package)?;
 
-        let package_installer = self
-            .package_installers
-            .get_mut(&package.installation_tool)
-            .ok_or(Error::InstallationToolDoesNotExist)?;
+        Ok(())
+    }
+
+    pub fn update_package(
+        &mut self,
+        package_name: String,
+        installer: Option<InstallerId>,
+    ) -> Result<()> {
+        let package = self.resolve_package(&package_name, installer)?;
 
-        package_installer.update_package(&package.package_name)?;
+        if let Err(error) = self
+            .installer_mut(&package.installer())?
+            .update_package(&package)
+        {
+            return Err(self.interrupted(package, error));
+        }
 
         Ok(())
     }
 
+    // Every installer has to list its packages before the store is touched, a
+    // tool that fails to list would otherwise look like it has nothing
+    // installed
     pub fn sync_packages(&mut self) -> Result<()> {
         let mut packages_to_add = Vec::new();
 
-        for package_installer in self.package_installers.values() {
+        for (installer, package_installer) in &self.package_installers {
             let packages_data = package_installer
                 .get_packages()
+                .map_err(|e| Error::ListingPackages(installer.clone(), e))?
                 .into_iter()
-                .map(|raw_data| raw_data.to_package_data(true));
+                .map(|raw_data| raw_data.to_package_data(installer.instance.clone(), true));
 
             packages_to_add.extend(packages_data);
         }
 
         for package_data in packages_to_add {
-            if !self.store.package_exists(&package_data.package_name)? {
+            if !self.store.package_exists(&package_data.key())? {
                 self.store.store_package(PackageData {
-                    package_name: package_data.package_name,
-                    installation_tool: package_data.installation_tool,
                     category_name: "default".to_string(),
-                    installed: package_data.installed,
+                    ..package_data
                 })?;
             }
         }
@@ -257,6 +455,122 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
         Ok(())
     }
 
+    // The stored package with what its installer knows about it, installers
+    // that are unavailable give no information
+    pub fn package_info(
+        &self,
+        package_name: String,
+        installer: Option<InstallerId>,
+    ) -> Result<PackageDetails> {
+        let package = self.resolve_package(&package_name, installer)?;
+
+        let info = match self.package_installers.get(&package.installer()) {
+            Some(package_installer) => package_installer.info(&package)?,
+            None => None,
+        };
+
+        Ok(PackageDetails { package, info })
+    }
+
+    // Disk usage of the installers that have tracked packages, by package
+    fn disk_usage(&self, packages: &[PackageData]) -> Result<HashMap<PackageKey, PackageUsage>> {
+        let installers: BTreeSet<InstallerId> =
+            packages.iter().map(PackageData::installer).collect();
+        let mut usage = HashMap::new();
+
+        for installer in installers {
+            let Some(package_installer) = self.package_installers.get(&installer) else {
+                continue;
+            };
+
+            for package_usage in package_installer.disk_usage()? {
+                usage.insert(
+                    PackageKey::new(installer.clone(), package_usage.package_name.clone()),
+                    package_usage,
+                );
+            }
+        }
+
+        Ok(usage)
+    }
+
+    // Key the installer reports the disk usage of the package under
+    fn installed_key(&self, package: &PackageData) -> PackageKey {
+        let installer = package.installer();
+        let installed_name = self
+            .package_installers
+            .get(&installer)
+            .map_or(package.package_name.as_str(), |package_installer| {
+                package_installer.installed_name(package)
+            });
+
+        PackageKey::new(installer, installed_name)
+    }
+
+    pub fn category_stats(&self) -> Result<Vec<CategoryStats>> {
+        let packages = self.store.get_packages()?;
+        let usage = self.disk_usage(&packages)?;
+
+        // Everything the installed packages of each category need
+        let mut needed: Vec<(Category, BTreeSet<PackageKey>)> = Vec::new();
+        for category in self.store.get_categories()? {
+            let mut closure = BTreeSet::new();
+            let mut pending: Vec<PackageKey> = packages
+                .iter()
+                .filter(|package| package.category_name == category.category_name)
+                .filter(|package| package.installed)
+                .map(|package| self.installed_key(package))
+                .collect();
+
+            while let Some(key) = pending.pop() {
+                if let Some(package_usage) = usage.get(&key) {
+                    pending.extend(
+                        package_usage
+                            .dependencies
+                            .iter()
+                            .map(|dependency| PackageKey::new(key.installer(), dependency))
+                            .filter(|dependency| !closure.contains(dependency)),
+                    );
+                }
+                closure.insert(key);
+            }
+
+            needed.push((category, closure));
+        }
+
+        let mut needing_categories: HashMap<&PackageKey, usize> = HashMap::new();
+        for key in needed.iter().flat_map(|(_, closure)| closure) {
+            *needing_categories.entry(key).or_default() += 1;
+        }
+
+        let size = |key: &PackageKey| usage.get(key).map_or(0, |package_usage| package_usage.size);
+
+        Ok(needed
+            .iter()
+            .map(|(category, closure)| {
+                let category_packages: Vec<&PackageData> = packages
+                    .iter()
+                    .filter(|package| package.category_name == category.category_name)
+                    .collect();
+
+                CategoryStats {
+                    category_name: category.category_name.clone(),
+                    packages: category_packages.len(),
+                    installed: category_packages
+                        .iter()
+                        .filter(|package| package.installed)
+                        .count(),
+                    installed_size: closure.iter().map(size).sum(),
+                    exclusive_size: closure
+                        .iter()
+                        .filter(|key| needing_categories[key] == 1)
+                        .map(size)
+                        .sum(),
+                }
+            })
+            .collect())
+    }
+
     pub fn get_packages(&self) -> Result<Vec<PackageData>> {
         Ok(self.store.get_packages()?)
     }
@@ -269,3 +583,502 @@ impl<T: PazckagerStorage> PazckagerCore<T> {
         Ok(self.store.get_packages_by_category(&category_name)?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use models::{InstallationTools, PackageInfo, PazckagerCoreBuilder};
+    use testing::{FakeInstaller, MemoryStorage};
+
+    fn test_core(installer: &FakeInstaller) -> PazckagerCore<MemoryStorage> {
+        PazckagerCoreBuilder::new(MemoryStorage::new())
+            .with_installer(installer.clone())
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_install_category() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman);
+        let mut core = test_core(&installer);
+
+        core.add_category("dev".to_string(), None)?;
+        core.add_package(
+            "ripgrep".to_string(),
+            InstallationTools::Pacman.into(),
+            Some("dev".to_string()),
+            PackageMetadata::new(),
+        )?;
+        core.add_package(
+            "firefox".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::new(),
+        )?;
+
+        core.install_category("dev".to_string())?;
+
+        assert_eq!(installer.installed_packages(), vec!["ripgrep".to_string()]);
+        assert_eq!(installer.batch_calls(), vec!["begin", "end"]);
+        assert!(
+            core.get_packages()?
+                .iter()
+                .all(|package| { package.installed == (package.package_name == "ripgrep") })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_install_category() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman).interrupted_on("git");
+        let mut core = test_core(&installer);
+
+        core.add_category("dev".to_string(), None)?;
+        for package_name in ["bat", "git", "zsh"] {
+            core.add_package(
+                package_name.to_string(),
+                InstallationTools::Pacman.into(),
+                Some("dev".to_string()),
+                PackageMetadata::new(),
+            )?;
+        }
+
+        let report = core.install_category("dev".to_string())?;
+
+        assert_eq!(
+            report,
+            OperationReport {
+                status: OperationStatus::Cancelled,
+                completed: vec![PackageKey::new(InstallationTools::Pacman, "bat")],
+                remaining: vec![
+                    PackageKey::new(InstallationTools::Pacman, "git"),
+                    PackageKey::new(InstallationTools::Pacman, "zsh"),
+                ],
+            }
+        );
+        assert_eq!(installer.batch_calls(), vec!["begin", "end"]);
+        // git was installed before the tool was stopped
+        assert_eq!(
+            core.get_packages()?
+                .into_iter()
+                .map(|package| (package.package_name, package.installed))
+                .collect::<Vec<_>>(),
+            vec![
+                ("bat".to_string(), true),
+                ("git".to_string(), true),
+                ("zsh".to_string(), false),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_interrupted_install_checks_the_provider() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman).interrupted_on("jack");
+        let mut core = test_core(&installer);
+
+        core.add_package(
+            "jack".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::from([("provider".to_string(), "pipewire-jack".to_string())]),
+        )?;
+
+        assert!(core.install_package("jack".to_string(), None).is_err());
+        assert_eq!(
+            installer.installed_packages(),
+            vec!["pipewire-jack".to_string()]
+        );
+        assert!(core.get_packages()?[0].installed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancelled_token_stops_operations() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman);
+        let cancellation = CancellationToken::new();
+        let mut core = PazckagerCoreBuilder::new(MemoryStorage::new())
+            .with_installer(installer.clone())
+            .with_cancellation(cancellation.clone())
+            .build()
+            .unwrap();
+
+        core.add_package(
+            "ripgrep".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::new(),
+        )?;
+        cancellation.cancel();
+
+        let report = core.install_category("default".to_string())?;
+
+        assert_eq!(report.status, OperationStatus::Cancelled);
+        assert!(report.completed.is_empty());
+        assert!(installer.installed_packages().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_package_without_installer() {
+        let installer = FakeInstaller::new(InstallationTools::Pacman);
+        let mut core = test_core(&installer);
+
+        let result = core.add_package(
+            "yay".to_string(),
+            InstallationTools::Yay.into(),
+            None,
+            PackageMetadata::new(),
+        );
+
+        assert!(matches!(result, Err(Error::InstallationToolDoesNotExist)));
+    }
+
+    #[test]
+    fn test_unavailable_installer_is_skipped() {
+        let pacman = FakeInstaller::new(InstallationTools::Pacman);
+        let apt = FakeInstaller::new(InstallationTools::Apt).unavailable();
+        let mut core = PazckagerCoreBuilder::new(MemoryStorage::new())
+            .with_installer(pacman)
+            .with_installer(apt)
+            .build()
+            .unwrap();
+
+        let result = core.add_package(
+            "ripgrep".to_string(),
+            InstallationTools::Apt.into(),
+            None,
+            PackageMetadata::new(),
+        );
+
+        assert!(matches!(
+            result,
+            Err(Error::InstallationToolUnavailable(installer))
+                if installer == InstallationTools::Apt.into()
+        ));
+        assert_eq!(
+            core.tools()
+                .into_iter()
+                .map(|tool| (
+                    tool.installer.installation_tool,
+                    tool.available,
+                    tool.version
+                ))
+                .collect::<Vec<_>>(),
+            vec![
+                (InstallationTools::Apt, false, None),
+                (
+                    InstallationTools::Pacman,
+                    true,
+                    Some("fake 1.0.0".to_string())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sync_packages() -> Result<()> {
+        let installer =
+            FakeInstaller::new(InstallationTools::Pacman).with_installed(["git", "zsh"]);
+        let mut core = test_core(&installer);
+
+        core.sync_packages()?;
+
+        let packages = core.get_package_by_category("default".to_string())?;
+        assert_eq!(packages.len(), 2);
+        assert!(packages.iter().all(|package| package.installed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_packages_stops_when_listing_fails() {
+        let pacman = FakeInstaller::new(InstallationTools::Pacman).with_installed(["git"]);
+        let flatpak = FakeInstaller::new(InstallationTools::Flatpak).failing_listing();
+        let mut core = PazckagerCoreBuilder::new(MemoryStorage::new())
+            .with_installer(pacman)
+            .with_installer(flatpak)
+            .build()
+            .unwrap();
+
+        let result = core.sync_packages();
+
+        assert!(matches!(
+            result,
+            Err(Error::ListingPackages(installer, _))
+                if installer == InstallationTools::Flatpak.into()
+        ));
+        assert!(core.get_packages().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_delete_category_moves_packages_to_default() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman);
+        let mut core = test_core(&installer);
+
+        core.add_category("dev".to_string(), None)?;
+        core.add_package(
+            "ripgrep".to_string(),
+            InstallationTools::Pacman.into(),
+            Some("dev".to_string()),
+            PackageMetadata::new(),
+        )?;
+
+        core.delete_category("dev".to_string())?;
+
+        assert!(core.get_category("dev".to_string()).is_err());
+        assert_eq!(
+            core.get_package_by_category("default".to_string())?[0].package_name,
+            "ripgrep"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_install_keeps_package_uninstalled() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman).failing_on("broken");
+        let mut core = test_core(&installer);
+
+        core.add_package(
+            "broken".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::new(),
+        )?;
+
+        assert!(core.install_package("broken".to_string(), None).is_err());
+        assert!(!core.get_packages()?[0].installed);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uninstall_marks_package_uninstalled() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman)
+            .with_installed(["git", "zsh"])
+            .interrupted_on("zsh");
+        let mut core = test_core(&installer);
+
+        core.sync_packages()?;
+        core.uninstall_package("git".to_string(), None)?;
+        // zsh was removed before the tool was stopped
+        assert!(
+            core.uninstall_package("zsh".to_string(), None)
+                .is_err_and(|err| err.is_cancelled())
+        );
+
+        assert!(installer.installed_packages().is_empty());
+        assert!(
+            core.get_packages()?
+                .iter()
+                .all(|package| !package.installed)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_install_stores_resolved_metadata() -> Result<()> {
+        let installer =
+            FakeInstaller::new(InstallationTools::Pacman).resolving("provider", "pipewire-jack");
+        let mut core = test_core(&installer);
+
+        core.add_package(
+            "jack".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::new(),
+        )?;
+        core.install_package("jack".to_string(), None)?;
+
+        assert_eq!(
+            core.get_packages()?[0].metadata,
+            PackageMetadata::from([("provider".to_string(), "pipewire-jack".to_string())])
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_info() -> Result<()> {
+        let info = PackageInfo {
+            description: Some("Fast line-oriented search tool".to_string()),
+            version: Some("14.1.1-1".to_string()),
+            ..PackageInfo::default()
+        };
+        let installer = FakeInstaller::new(InstallationTools::Pacman).describing(info.clone());
+        let mut core = test_core(&installer);
+
+        core.add_package(
+            "ripgrep".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::new(),
+        )?;
+
+        let details = core.package_info("ripgrep".to_string(), None)?;
+
+        assert_eq!(details.package.category_name, "default");
+        assert_eq!(details.info, Some(info));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_category_stats() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman)
+            .with_installed(["pcre2", "ffmpeg", "glibc"])
+            .using_disk("ripgrep", 4, &["pcre2", "glibc"])
+            .using_disk("pcre2", 2, &["glibc"])
+            .using_disk("mpv", 8, &["ffmpeg", "glibc"])
+            .using_disk("ffmpeg", 32, &["glibc"])
+            .using_disk("glibc", 64, &[]);
+        let mut core = test_core(&installer);
+
+        core.add_category("dev".to_string(), None)?;
+        core.add_category("media".to_string(), None)?;
+        for (package_name, category_name) in [("ripgrep", "dev"), ("fd", "dev"), ("mpv", "media")] {
+            core.add_package(
+                package_name.to_string(),
+                InstallationTools::Pacman.into(),
+                Some(category_name.to_string()),
+                PackageMetadata::new(),
+            )?;
+        }
+        core.install_package("ripgrep".to_string(), None)?;
+        core.install_package("mpv".to_string(), None)?;
+
+        let stats = core.category_stats()?;
+
+        assert_eq!(
+            stats.iter().find(|stats| stats.category_name == "dev"),
+            Some(&CategoryStats {
+                category_name: "dev".to_string(),
+                packages: 2,
+                installed: 1,
+                installed_size: 70,
+                exclusive_size: 6,
+            })
+        );
+        assert_eq!(
+            stats
+                .iter()
+                .find(|stats| stats.category_name == "media")
+                .map(|stats| (stats.installed_size, stats.exclusive_size)),
+            Some((104, 40))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_category_stats_count_the_provider() -> Result<()> {
+        let installer = FakeInstaller::new(InstallationTools::Pacman)
+            .using_disk("pipewire-jack", 16, &["glibc"])
+            .using_disk("glibc", 64, &[]);
+        let mut core = test_core(&installer);
+
+        core.add_package(
+            "jack".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::from([("provider".to_string(), "pipewire-jack".to_string())]),
+        )?;
+        core.install_package("jack".to_string(), None)?;
+
+        assert_eq!(
+            core.category_stats()?
+                .iter()
+                .map(|stats| stats.installed_size)
+                .collect::<Vec<_>>(),
+            vec![80]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_same_name_under_different_tools() -> Result<()> {
+        let pacman = FakeInstaller::new(InstallationTools::Pacman);
+        let yay = FakeInstaller::new(InstallationTools::Yay);
+        let mut core = PazckagerCoreBuilder::new(MemoryStorage::new())
+            .with_installer(pacman.clone())
+            .with_installer(yay.clone())
+            .build()
+            .unwrap();
+
+        core.add_package(
+            "ripgrep".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::new(),
+        )?;
+        core.add_package(
+            "ripgrep".to_string(),
+            InstallationTools::Yay.into(),
+            None,
+            PackageMetadata::new(),
+        )?;
+
+        assert!(matches!(
+            core.install_package("ripgrep".to_string(), None),
+            Err(Error::AmbiguousPackage(_, _))
+        ));
+
+        core.install_package("ripgrep".to_string(), Some(InstallationTools::Yay.into()))?;
+
+        assert!(yay.is_installed("ripgrep"));
+        assert!(!pacman.is_installed("ripgrep"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_named_installer_instances() -> Result<()> {
+        let host = FakeInstaller::new(InstallationTools::Pacman);
+        let chroot = FakeInstaller::new(InstallationTools::Pacman).with_installed(["base"]);
+        let chroot_installer =
+            InstallerId::new(InstallationTools::Pacman, Some("chroot".to_string()));
+        let mut core = PazckagerCoreBuilder::new(MemoryStorage::new())
+            .with_installer(host.clone())
+            .with_named_installer("chroot", chroot.clone())
+            .build()
+            .unwrap();
+
+        core.add_package(
+            "ripgrep".to_string(),
+            InstallationTools::Pacman.into(),
+            None,
+            PackageMetadata::new(),
+        )?;
+        core.add_package(
+            "ripgrep".to_string(),
+            chroot_installer.clone(),
+            None,
+            PackageMetadata::new(),
+        )?;
+        core.install_package("ripgrep".to_string(), Some(chroot_installer.clone()))?;
+        core.sync_packages()?;
+
+        assert!(chroot.is_installed("ripgrep"));
+        assert!(!host.is_installed("ripgrep"));
+        assert_eq!(
+            core.get_packages()?
+                .into_iter()
+                .map(|package| package.key().to_string())
+                .collect::<Vec<_>>(),
+            vec![
+                "pacman/ripgrep",
+                "pacman@chroot/base",
+                "pacman@chroot/ripgrep"
+            ]
+        );
+
+        Ok(())
+    }
+}
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    str::FromStr,
+    time::Duration,
+};
 
 use crate::err::Error;
 use partial_struct::Partial;
@@ -7,45 +12,272 @@ use thiserror::Error;
 
 use crate::{
     PazckagerCore,
-    traits::{InstallationTool, PazckagerStorage},
+    cancellation::CancellationToken,
+    program::RunControl,
+    traits::{InstallationTool, PazckagerStorage, ToolStatus},
 };
 
-#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, Clone, Debug)]
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum InstallationTools {
     Pacman,
     Yay,
+    Flatpak,
+    Cargo,
+    Pipx,
+    Npm,
+    Apt,
+    Dnf,
+    Nix,
+    // Installer defined through command templates, identified by its name
+    Script(String),
+    // Out of process installer speaking the plugin protocol, identified by
+    // its name
+    Plugin(String),
 }
 
 impl FromStr for InstallationTools {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(script_name) = s.strip_prefix("script:") {
+            return Ok(InstallationTools::Script(script_name.to_string()));
+        }
+
+        if let Some(plugin_name) = s.strip_prefix("plugin:") {
+            return Ok(InstallationTools::Plugin(plugin_name.to_string()));
+        }
+
         Ok(match s.to_lowercase().as_str() {
             "pacman" => InstallationTools::Pacman,
-            "yay" => InstallationTools::Pacman,
+            "yay" => InstallationTools::Yay,
+            "flatpak" => InstallationTools::Flatpak,
+            "cargo" => InstallationTools::Cargo,
+            "pipx" => InstallationTools::Pipx,
+            "npm" => InstallationTools::Npm,
+            "apt" => InstallationTools::Apt,
+            "dnf" => InstallationTools::Dnf,
+            "nix" => InstallationTools::Nix,
             _ => Err("Tool is not supported")?,
         })
     }
 }
 
+impl Display for InstallationTools {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstallationTools::Pacman => write!(f, "pacman"),
+            InstallationTools::Yay => write!(f, "yay"),
+            InstallationTools::Flatpak => write!(f, "flatpak"),
+            InstallationTools::Cargo => write!(f, "cargo"),
+            InstallationTools::Pipx => write!(f, "pipx"),
+            InstallationTools::Npm => write!(f, "npm"),
+            InstallationTools::Apt => write!(f, "apt"),
+            InstallationTools::Dnf => write!(f, "dnf"),
+            InstallationTools::Nix => write!(f, "nix"),
+            InstallationTools::Script(script_name) => write!(f, "script:{script_name}"),
+            InstallationTools::Plugin(plugin_name) => write!(f, "plugin:{plugin_name}"),
+        }
+    }
+}
+
+// Identifies a registered installer. The same installation tool can be
+// registered several times under different instance names, like a pacman for
+// a chroot next to the host one, the unnamed instance is the default one.
+// Written as "pacman" or "pacman@chroot".
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct InstallerId {
+    pub installation_tool: InstallationTools,
+    pub instance: Option<String>,
+}
+
+impl InstallerId {
+    pub fn new(installation_tool: InstallationTools, instance: Option<String>) -> Self {
+        Self {
+            installation_tool,
+            instance,
+        }
+    }
+}
+
+impl From<InstallationTools> for InstallerId {
+    fn from(installation_tool: InstallationTools) -> Self {
+        Self::new(installation_tool, None)
+    }
+}
+
+impl FromStr for InstallerId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (installation_tool, instance) = match s.split_once('@') {
+            Some((_, "")) => Err("Instance name can't be empty")?,
+            Some((installation_tool, instance)) => (installation_tool, Some(instance.to_string())),
+            None => (s, None),
+        };
+
+        Ok(Self::new(installation_tool.parse()?, instance))
+    }
+}
+
+impl Display for InstallerId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.instance {
+            Some(instance) => write!(f, "{}@{instance}", self.installation_tool),
+            None => write!(f, "{}", self.installation_tool),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 pub struct Category {
     pub category_name: String,
     pub additional_info: Option<String>,
 }
 
+// Tool specific options of a package, like the remote of a flatpak app
+pub type PackageMetadata = BTreeMap<String, String>;
+
 #[derive(Serialize, Deserialize, Partial, Clone, PartialEq, Eq, Debug)]
-#[partial("RawPackageData", omit(installed))]
+#[partial(
+    "RawPackageData",
+    derive(Serialize, Deserialize),
+    omit(instance, installed)
+)]
 pub struct PackageData {
     pub package_name: String,
     pub installation_tool: InstallationTools,
+    // Installer instance owning the package, none for the default instance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
     pub category_name: String,
     pub installed: bool,
+    #[serde(default, skip_serializing_if = "PackageMetadata::is_empty")]
+    pub metadata: PackageMetadata,
+}
+
+impl PackageData {
+    pub fn key(&self) -> PackageKey {
+        PackageKey::new(self.installer(), self.package_name.clone())
+    }
+
+    pub fn installer(&self) -> InstallerId {
+        InstallerId::new(self.installation_tool.clone(), self.instance.clone())
+    }
+
+    // The name with the version pinned in the metadata under the key, joined
+    // the way the tool expects, like "typescript@5.4" or "black==24.3.0"
+    pub fn versioned_name(&self, version_key: &str, separator: &str) -> String {
+        match self.metadata.get(version_key) {
+            Some(version) => format!("{}{separator}{version}", self.package_name),
+            None => self.package_name.clone(),
+        }
+    }
+}
+
+// Packages are identified by their installer plus their name, so the same
+// name can be tracked once per tool and instance
+#[derive(Serialize, Deserialize, Hash, PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct PackageKey {
+    pub installation_tool: InstallationTools,
+    pub instance: Option<String>,
+    pub package_name: String,
+}
+
+impl PackageKey {
+    pub fn new(installer: impl Into<InstallerId>, package_name: impl Into<String>) -> Self {
+        let installer = installer.into();
+
+        Self {
+            installation_tool: installer.installation_tool,
+            instance: installer.instance,
+            package_name: package_name.into(),
+        }
+    }
+
+    pub fn installer(&self) -> InstallerId {
+        InstallerId::new(self.installation_tool.clone(), self.instance.clone())
+    }
+}
+
+impl Display for PackageKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.installer(), self.package_name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OperationStatus {
+    #[default]
+    Completed,
+    Cancelled,
+}
+
+impl Display for OperationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OperationStatus::Completed => write!(f, "completed"),
+            OperationStatus::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+// Outcome of an operation on several packages. A cancelled operation leaves
+// the remaining packages as they were, including the one it was stopped on
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OperationReport {
+    pub status: OperationStatus,
+    pub completed: Vec<PackageKey>,
+    pub remaining: Vec<PackageKey>,
+}
+
+// What the installation tool knows about a package, fields it can't tell
+// are left empty
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageInfo {
+    pub description: Option<String>,
+    pub version: Option<String>,
+    pub repository: Option<String>,
+    pub url: Option<String>,
+    pub licenses: Vec<String>,
+    pub installed_size: Option<String>,
+    pub dependencies: Vec<String>,
+    pub required_by: Vec<String>,
+    pub install_date: Option<String>,
+}
+
+// A tracked package with the tool information, when its installer has any
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackageDetails {
+    pub package: PackageData,
+    pub info: Option<PackageInfo>,
+}
+
+// Disk usage of a package installed by a tool, the dependencies are the
+// names of other packages of the same tool
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageUsage {
+    pub package_name: String,
+    // Installed size in bytes
+    pub size: u64,
+    pub dependencies: Vec<String>,
+}
+
+// Sizes count the tracked packages of the category with all their
+// dependencies, the exclusive size only what no other category needs
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CategoryStats {
+    pub category_name: String,
+    pub packages: usize,
+    pub installed: usize,
+    pub installed_size: u64,
+    pub exclusive_size: u64,
 }
 
 pub struct PazckagerCoreBuilder<T> {
     store: T,
-    package_installers: HashMap<InstallationTools, Box<dyn InstallationTool>>,
+    package_installers: HashMap<InstallerId, Box<dyn InstallationTool>>,
+    unavailable_installers: HashMap<InstallerId, ToolStatus>,
+    run_control: RunControl,
 }
 
 impl<T: PazckagerStorage> PazckagerCoreBuilder<T> {
@@ -53,22 +285,79 @@ impl<T: PazckagerStorage> PazckagerCoreBuilder<T> {
         Self {
             store,
             package_installers: HashMap::new(),
+            unavailable_installers: HashMap::new(),
+            run_control: RunControl::default(),
         }
     }
 
-    pub fn with_installer(mut self, installation_tool: impl InstallationTool + 'static) -> Self {
-        self.package_installers
-            .insert(installation_tool.get_type(), Box::new(installation_tool));
+    // Cancelling the token stops the running tool and the rest of the
+    // operation
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.run_control.cancellation = cancellation;
+        self
+    }
 
+    // Longest a single tool command may run
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.run_control.timeout = Some(timeout);
         self
     }
 
-    pub fn build(self) -> Result<PazckagerCore<T>, BuilderError> {
-        if self.package_installers.is_empty() {
-            Err(BuilderError::NoPackageInstaller)
+    // Registers the default instance of the installation tool
+    pub fn with_installer(self, installation_tool: impl InstallationTool + 'static) -> Self {
+        let installer = InstallerId::from(installation_tool.get_type());
+
+        self.register(installer, installation_tool)
+    }
+
+    // Registers another instance of an installation tool, its packages are
+    // tracked apart from the ones of the default instance
+    pub fn with_named_installer(
+        self,
+        instance: impl Into<String>,
+        installation_tool: impl InstallationTool + 'static,
+    ) -> Self {
+        let installer = InstallerId::new(installation_tool.get_type(), Some(instance.into()));
+
+        self.register(installer, installation_tool)
+    }
+
+    // Installers whose tool is not available are skipped, they are only
+    // remembered to report them and to explain why their packages can't be
+    // handled
+    fn register(
+        mut self,
+        installer: InstallerId,
+        installation_tool: impl InstallationTool + 'static,
+    ) -> Self {
+        if installation_tool.is_available() {
+            self.unavailable_installers.remove(&installer);
+            self.package_installers
+                .insert(installer, Box::new(installation_tool));
         } else {
-            Ok(PazckagerCore::new(self.store, self.package_installers)?)
+            self.package_installers.remove(&installer);
+            let tool_status = ToolStatus::of(installer.clone(), &installation_tool);
+            self.unavailable_installers.insert(installer, tool_status);
+        }
+
+        self
+    }
+
+    pub fn build(mut self) -> Result<PazckagerCore<T>, BuilderError> {
+        if self.package_installers.is_empty() && self.unavailable_installers.is_empty() {
+            return Err(BuilderError::NoPackageInstaller);
+        }
+
+        for package_installer in self.package_installers.values_mut() {
+            package_installer.set_run_control(self.run_control.clone());
         }
+
+        Ok(PazckagerCore::new(
+            self.store,
+            self.package_installers,
+            self.unavailable_installers,
+            self.run_control,
+        )?)
     }
 }
 
@@ -79,3 +368,49 @@ pub enum BuilderError {
     #[error("Pazckager core error: {0}")]
     PazckagerCore(#[from] Error),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_installation_tools_round_trip() {
+        for installation_tool in [
+            InstallationTools::Pacman,
+            InstallationTools::Flatpak,
+            InstallationTools::Script("go".to_string()),
+            InstallationTools::Plugin("internal".to_string()),
+        ] {
+            assert_eq!(
+                installation_tool.to_string().parse::<InstallationTools>(),
+                Ok(installation_tool)
+            );
+        }
+
+        assert_eq!(
+            InstallationTools::Script("go".to_string()).to_string(),
+            "script:go"
+        );
+        assert!("brew".parse::<InstallationTools>().is_err());
+    }
+
+    #[test]
+    fn test_installer_id_round_trip() {
+        for installer in [
+            InstallerId::from(InstallationTools::Pacman),
+            InstallerId::new(InstallationTools::Pacman, Some("chroot".to_string())),
+            InstallerId::new(
+                InstallationTools::Script("go".to_string()),
+                Some("work".to_string()),
+            ),
+        ] {
+            assert_eq!(installer.to_string().parse::<InstallerId>(), Ok(installer));
+        }
+
+        assert_eq!(
+            InstallerId::new(InstallationTools::Flatpak, Some("system".to_string())).to_string(),
+            "flatpak@system"
+        );
+        assert!("pacman@".parse::<InstallerId>().is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use serde::Deserialize;
+use std::ffi::OsStr;
+use std::fs;
+use std::process::Command;
+
+use crate::program::find_program;
+
+// Elevation helpers tried by `Auto`, in order of preference
+const ELEVATION_PROGRAMS: [(&str, PermissionMethod); 4] = [
+    ("sudo", PermissionMethod::Sudo),
+    ("doas", PermissionMethod::Doas),
+    ("run0", PermissionMethod::Run0),
+    ("pkexec", PermissionMethod::Pkexec),
+];
+
+// How installers that change the system packages gain root privileges
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PermissionMethod {
+    Sudo,
+    Pkexec,
+    Doas,
+    Run0,
+    // Already running as root, the program runs without any wrapper
+    Root,
+    // Picks Root when the effective user is root, otherwise the first
+    // elevation helper that is installed
+    #[default]
+    Auto,
+}
+
+impl PermissionMethod {
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let wrapper = match self.resolve() {
+            PermissionMethod::Sudo => "sudo",
+            PermissionMethod::Pkexec => "pkexec",
+            PermissionMethod::Doas => "doas",
+            PermissionMethod::Run0 => "run0",
+            PermissionMethod::Root | PermissionMethod::Auto => return Command::new(program),
+        };
+
+        let mut command = Command::new(wrapper);
+        command.arg(program);
+        command
+    }
+
+    // The method commands actually run with, only differs from self for Auto
+    pub fn resolve(&self) -> PermissionMethod {
+        match self {
+            PermissionMethod::Auto => detect(is_effective_root(), |program| {
+                find_program(program).is_some()
+            }),
+            method => *method,
+        }
+    }
+}
+
+fn detect(is_root: bool, is_installed: impl Fn(&str) -> bool) -> PermissionMethod {
+    if is_root {
+        return PermissionMethod::Root;
+    }
+
+    ELEVATION_PROGRAMS
+        .iter()
+        .find(|(program, _)| is_installed(program))
+        .map(|(_, method)| *method)
+        // Nothing is installed, sudo at least gives a meaningful error
+        .unwrap_or(PermissionMethod::Sudo)
+}
+
+// The second Uid field of /proc/self/status is the effective UID
+fn is_effective_root() -> bool {
+    fs::read_to_string("/proc/self/status")
+        .ok()
+        .and_then(|status| {
+            status
+                .lines()
+                .find_map(|line| line.strip_prefix("Uid:"))
+                .and_then(|uids| uids.split_whitespace().nth(1).map(|uid| uid == "0"))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program_and_args(command: &Command) -> Vec<String> {
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_command_wrappers() {
+        assert_eq!(
+            program_and_args(&PermissionMethod::Doas.command("pacman")),
+            vec!["doas", "pacman"]
+        );
+        assert_eq!(
+            program_and_args(&PermissionMethod::Run0.command("apt-get")),
+            vec!["run0", "apt-get"]
+        );
+        assert_eq!(
+            program_and_args(&PermissionMethod::Root.command("dnf")),
+            vec!["dnf"]
+        );
+    }
+
+    #[test]
+    fn test_auto_detection() {
+        assert_eq!(detect(true, |_| true), PermissionMethod::Root);
+        assert_eq!(detect(false, |_| true), PermissionMethod::Sudo);
+        assert_eq!(
+            detect(false, |program| program == "run0" || program == "pkexec"),
+            PermissionMethod::Run0
+        );
+        assert_eq!(detect(false, |_| false), PermissionMethod::Sudo);
+    }
+}
@@ -0,0 +1,728 @@
+// Installers living outside of this repository run as separate executables,
+// named `pazckager-installer-<name>` and found on PATH, and talk to the core
+// with JSON messages over stdio, one message per line. A plugin is only
+// started once an operation needs it.
+//
+// Every request carries an id that the response repeats, with either a result
+// or an error:
+//
+// > {"id":1,"method":"install","params":{"package":{"package_name":"tool","metadata":{}}}}
+// < {"id":1,"result":null}
+// < {"id":1,"error":{"message":"tool is not available"}}
+//
+// The first request is always the handshake. The core sends the protocol
+// version it speaks and the optional capabilities it can use, the plugin
+// answers with its own protocol version and the capabilities it implements.
+// Versions have to match, and only the capabilities both sides know are used:
+//
+// > {"id":0,"method":"handshake","params":{"protocol_version":1,"capabilities":["search","info"]}}
+// < {"id":0,"result":{"protocol_version":1,"capabilities":["search"]}}
+//
+// Methods, mirroring `InstallationTool`:
+//
+// install, delete, update  {"package": package}        -> null
+// list                     {}                          -> [package]
+// search (capability)      {"query": "text"}           -> [package]
+// info (capability)        {"package_name": "name"}    -> {"key": "value"}
+//
+// where package is {"package_name": "name", "metadata": {"key": "value"}}.
+// The info keys shown to users are description, version, repository, url,
+// licenses, installed_size, dependencies, required_by and install_date, lists
+// are comma separated. The plugin should exit once its stdin is closed, a
+// plugin that takes longer to answer than the operation timeout or is still
+// busy when the operation is cancelled gets SIGINT, then SIGKILL.
+
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::Mutex;
+use std::time::Duration;
+use std::{env, fs};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use thiserror::Error;
+
+use crate::models::{InstallationTools, PackageData, PackageInfo, PackageMetadata, RawPackageData};
+use crate::program::{GRACE_PERIOD, OutputLines, RunControl, RunError, find_program, wait_or_kill};
+use crate::traits::{InstallationTool, ToolCapability, ToolError, ToolResult};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const EXECUTABLE_PREFIX: &str = "pazckager-installer-";
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PluginCapability {
+    Search,
+    Info,
+}
+
+impl PluginCapability {
+    const ALL: [PluginCapability; 2] = [PluginCapability::Search, PluginCapability::Info];
+
+    fn name(&self) -> &'static str {
+        match self {
+            PluginCapability::Search => "search",
+            PluginCapability::Info => "info",
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("Could not start plugin {0}: {1}")]
+    Spawn(String, String),
+    #[error("Plugin {0} communication failed: {1}")]
+    Io(String, String),
+    #[error("Plugin {0} sent an invalid message: {1}")]
+    InvalidMessage(String, String),
+    #[error("Plugin {0} speaks protocol version {1}, expected {PROTOCOL_VERSION}")]
+    UnsupportedVersion(String, u32),
+    #[error("Plugin {0} does not support {1}")]
+    MissingCapability(String, &'static str),
+    #[error("Plugin {0}: {1}")]
+    Plugin(String, String),
+    #[error("Plugin {0} was cancelled")]
+    Cancelled(String),
+    #[error("Plugin {0} timed out after {1} seconds")]
+    TimedOut(String, u64),
+}
+
+impl PluginError {
+    fn into_tool_error(self, to_error: fn(String) -> ToolError) -> ToolError {
+        match self {
+            PluginError::Cancelled(name) => ToolError::Cancelled(name),
+            PluginError::TimedOut(name, seconds) => ToolError::TimedOut(name, seconds),
+            error => to_error(error.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Request<'a> {
+    id: u64,
+    method: &'a str,
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: u64,
+    #[serde(default)]
+    result: Value,
+    error: Option<ResponseError>,
+}
+
+#[derive(Deserialize)]
+struct ResponseError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct HandshakeResult {
+    protocol_version: u32,
+    #[serde(default)]
+    capabilities: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PluginPackage {
+    package_name: String,
+    #[serde(default)]
+    metadata: PackageMetadata,
+}
+
+struct PluginProcess {
+    child: Child,
+    stdin: Option<ChildStdin>,
+    stdout: OutputLines,
+    next_id: u64,
+    capabilities: Vec<PluginCapability>,
+}
+
+impl PluginProcess {
+    // Spawns the plugin and shakes hands with it. The handshake gets at most
+    // HANDSHAKE_TIMEOUT, a plugin that doesn't answer can't hold up the
+    // operation that needed it
+    fn start(plugin_name: &str, program: &Path, control: &RunControl) -> Result<Self, PluginError> {
+        let mut child = Command::new(program)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|e| PluginError::Spawn(plugin_name.to_string(), e.to_string()))?;
+
+        let stdin = child.stdin.take();
+        let stdout = OutputLines::new(child.stdout.take().ok_or(PluginError::Spawn(
+            plugin_name.to_string(),
+            "missing stdout".to_string(),
+        ))?);
+
+        let mut process = PluginProcess {
+            child,
+            stdin,
+            stdout,
+            next_id: 0,
+            capabilities: Vec::new(),
+        };
+
+        let handshake_control = RunControl {
+            timeout: Some(
+                control
+                    .timeout
+                    .map_or(HANDSHAKE_TIMEOUT, |timeout| timeout.min(HANDSHAKE_TIMEOUT)),
+            ),
+            ..control.clone()
+        };
+        let handshake: HandshakeResult = parse_result(
+            plugin_name,
+            process.call(
+                plugin_name,
+                "handshake",
+                json!({
+                    "protocol_version": PROTOCOL_VERSION,
+                    "capabilities": PluginCapability::ALL.map(|capability| capability.name()),
+                }),
+                &handshake_control,
+            )?,
+        )?;
+
+        if handshake.protocol_version != PROTOCOL_VERSION {
+            return Err(PluginError::UnsupportedVersion(
+                plugin_name.to_string(),
+                handshake.protocol_version,
+            ));
+        }
+
+        process.capabilities = PluginCapability::ALL
+            .into_iter()
+            .filter(|capability| {
+                handshake
+                    .capabilities
+                    .iter()
+                    .any(|name| name == capability.name())
+            })
+            .collect();
+
+        Ok(process)
+    }
+
+    fn call(
+        &mut self,
+        plugin_name: &str,
+        method: &str,
+        params: Value,
+        control: &RunControl,
+    ) -> Result<Value, PluginError> {
+        let io_error = |e: std::io::Error| PluginError::Io(plugin_name.to_string(), e.to_string());
+        let invalid_message = |e: String| PluginError::InvalidMessage(plugin_name.to_string(), e);
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut request = serde_json::to_string(&Request { id, method, params })
+            .map_err(|e| invalid_message(e.to_string()))?;
+        request.push('\n');
+
+        let stdin = self.stdin.as_mut().ok_or(PluginError::Io(
+            plugin_name.to_string(),
+            "stdin closed".to_string(),
+        ))?;
+        stdin.write_all(request.as_bytes()).map_err(io_error)?;
+        stdin.flush().map_err(io_error)?;
+
+        let line = match control.read_line(&mut self.child, &self.stdout) {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                return Err(PluginError::Io(
+                    plugin_name.to_string(),
+                    "plugin exited".to_string(),
+                ));
+            }
+            Err(error) => {
+                self.stdin = None;
+                return Err(match error {
+                    RunError::TimedOut(timeout) => {
+                        PluginError::TimedOut(plugin_name.to_string(), timeout.as_secs())
+                    }
+                    _ => PluginError::Cancelled(plugin_name.to_string()),
+                });
+            }
+        };
+
+        let response: Response =
+            serde_json::from_str(&line).map_err(|e| invalid_message(e.to_string()))?;
+
+        if response.id != id {
+            return Err(invalid_message(format!(
+                "expected response {id}, got {}",
+                response.id
+            )));
+        }
+
+        match response.error {
+            Some(error) => Err(PluginError::Plugin(plugin_name.to_string(), error.message)),
+            None => Ok(response.result),
+        }
+    }
+}
+
+impl Drop for PluginProcess {
+    fn drop(&mut self) {
+        drop(self.stdin.take());
+        wait_or_kill(&mut self.child, GRACE_PERIOD);
+    }
+}
+
+pub struct PluginInstaller {
+    name: String,
+    program: PathBuf,
+    // Started by the first call, commands that don't need the plugin never
+    // run it
+    process: Mutex<Option<PluginProcess>>,
+    control: RunControl,
+}
+
+impl PluginInstaller {
+    // Runs `pazckager-installer-<name>` from PATH
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            program: PathBuf::from(format!("{EXECUTABLE_PREFIX}{name}")),
+            process: Mutex::new(None),
+            control: RunControl::default(),
+        }
+    }
+
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    // Starts the plugin to ask for them
+    pub fn capabilities(&self) -> Result<Vec<PluginCapability>, PluginError> {
+        self.with_process(|process| Ok(process.capabilities.clone()))
+    }
+
+    pub fn search(&self, query: &str) -> Result<Vec<RawPackageData>, PluginError> {
+        self.ensure_capability(PluginCapability::Search)?;
+
+        let packages: Vec<PluginPackage> =
+            parse_result(&self.name, self.call("search", json!({ "query": query }))?)?;
+
+        Ok(packages
+            .into_iter()
+            .map(|package| self.raw_package_data(package))
+            .collect())
+    }
+
+    pub fn info(&self, package_name: &str) -> Result<PackageMetadata, PluginError> {
+        self.ensure_capability(PluginCapability::Info)?;
+
+        parse_result(
+            &self.name,
+            self.call("info", json!({ "package_name": package_name }))?,
+        )
+    }
+
+    fn ensure_capability(&self, capability: PluginCapability) -> Result<(), PluginError> {
+        if !self.capabilities()?.contains(&capability) {
+            return Err(PluginError::MissingCapability(
+                self.name.clone(),
+                capability.name(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn with_process<T>(
+        &self,
+        f: impl FnOnce(&mut PluginProcess) -> Result<T, PluginError>,
+    ) -> Result<T, PluginError> {
+        let mut process = self
+            .process
+            .lock()
+            .map_err(|e| PluginError::Io(self.name.clone(), e.to_string()))?;
+
+        let process = match &mut *process {
+            Some(process) => process,
+            process => process.insert(PluginProcess::start(
+                &self.name,
+                &self.program,
+                &self.control,
+            )?),
+        };
+
+        f(process)
+    }
+
+    fn call(&self, method: &str, params: Value) -> Result<Value, PluginError> {
+        self.with_process(|process| process.call(&self.name, method, params, &self.control))
+    }
+
+    fn call_with_package(&self, method: &str, package: &PackageData) -> Result<(), PluginError> {
+        self.call(
+            method,
+            json!({
+                "package": PluginPackage {
+                    package_name: package.package_name.clone(),
+                    metadata: package.metadata.clone(),
+                }
+            }),
+        )?;
+
+        Ok(())
+    }
+
+    fn raw_package_data(&self, package: PluginPackage) -> RawPackageData {
+        RawPackageData {
+            package_name: package.package_name,
+            installation_tool: self.get_type(),
+            category_name: String::new(),
+            metadata: package.metadata,
+        }
+    }
+}
+
+fn parse_result<T: DeserializeOwned>(plugin_name: &str, result: Value) -> Result<T, PluginError> {
+    serde_json::from_value(result)
+        .map_err(|e| PluginError::InvalidMessage(plugin_name.to_string(), e.to_string()))
+}
+
+// Info a plugin answered with as metadata, lists are comma separated
+fn package_info(mut metadata: PackageMetadata) -> PackageInfo {
+    let mut list = |key: &str| {
+        metadata
+            .remove(key)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|item| !item.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    let licenses = list("licenses");
+    let dependencies = list("dependencies");
+    let required_by = list("required_by");
+
+    PackageInfo {
+        description: metadata.remove("description"),
+        version: metadata.remove("version"),
+        repository: metadata.remove("repository"),
+        url: metadata.remove("url"),
+        licenses,
+        installed_size: metadata.remove("installed_size"),
+        dependencies,
+        required_by,
+        install_date: metadata.remove("install_date"),
+    }
+}
+
+// Names of the plugins found in the PATH directories
+pub fn discover_plugins() -> Vec<String> {
+    let Some(path) = env::var_os("PATH") else {
+        return Vec::new();
+    };
+
+    discover_plugins_in(env::split_paths(&path))
+}
+
+fn discover_plugins_in(directories: impl Iterator<Item = PathBuf>) -> Vec<String> {
+    let mut plugin_names: Vec<String> = directories
+        .filter_map(|directory| fs::read_dir(directory).ok())
+        .flat_map(|entries| entries.filter_map(Result::ok))
+        .filter(|entry| entry.path().is_file())
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_str()?
+                .strip_prefix(EXECUTABLE_PREFIX)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+        })
+        .collect();
+
+    plugin_names.sort();
+    plugin_names.dedup();
+    plugin_names
+}
+
+impl InstallationTool for PluginInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Plugin(self.name.clone())
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        find_program(&self.program).is_some()
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.call_with_package("install", package)
+            .map_err(|e| e.into_tool_error(ToolError::InstallingPackage))
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.call_with_package("delete", package)
+            .map_err(|e| e.into_tool_error(ToolError::DeletingPackage))
+    }
+
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.call_with_package("update", package)
+            .map_err(|e| e.into_tool_error(ToolError::UpdatingPackage))
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        self.call("list", json!({}))
+            .and_then(|result| parse_result::<Vec<PluginPackage>>(&self.name, result))
+            .map(|packages| {
+                packages
+                    .into_iter()
+                    .map(|package| self.raw_package_data(package))
+                    .collect()
+            })
+            .map_err(|e| e.into_tool_error(ToolError::ListingPackages))
+    }
+
+    fn info(&self, package: &PackageData) -> ToolResult<Option<PackageInfo>> {
+        let capabilities = self
+            .capabilities()
+            .map_err(|e| e.into_tool_error(ToolError::QueryingPackage))?;
+
+        if !capabilities.contains(&PluginCapability::Info) {
+            return Ok(None);
+        }
+
+        PluginInstaller::info(self, &package.package_name)
+            .map(|metadata| Some(package_info(metadata)))
+            .map_err(|e| e.into_tool_error(ToolError::QueryingPackage))
+    }
+
+    // A plugin that can't be started has none
+    fn capabilities(&self) -> BTreeSet<ToolCapability> {
+        PluginInstaller::capabilities(self)
+            .unwrap_or_default()
+            .iter()
+            .map(|capability| match capability {
+                PluginCapability::Search => ToolCapability::Search,
+                PluginCapability::Info => ToolCapability::Info,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{self, calls, fake_program};
+    use std::path::Path;
+    use std::time::{Duration, Instant};
+
+    // Answers the handshake with the given version and the search capability,
+    // records the other requests next to itself and fails on "broken"
+    fn fake_plugin(dir: &Path, protocol_version: u32) -> PathBuf {
+        let script = format!(
+            r#"#!/bin/sh
+while read -r line; do
+    id=$(echo "$line" | sed 's/.*"id":\([0-9]*\).*/\1/')
+    case "$line" in
+        *'"method":"handshake"'*)
+            echo "{{\"id\":$id,\"result\":{{\"protocol_version\":{protocol_version},\"capabilities\":[\"search\",\"teleport\"]}}}}" ;;
+        *broken*)
+            echo "{{\"id\":$id,\"error\":{{\"message\":\"broken package\"}}}}" ;;
+        *'"method":"list"'*)
+            echo "{{\"id\":$id,\"result\":[{{\"package_name\":\"tool\",\"metadata\":{{\"channel\":\"stable\"}}}}]}}" ;;
+        *'"method":"search"'*)
+            echo "{{\"id\":$id,\"result\":[{{\"package_name\":\"tool-extra\"}}]}}" ;;
+        *)
+            echo "$line" >> "$0.calls"
+            echo "{{\"id\":$id,\"result\":null}}" ;;
+    esac
+done
+"#
+        );
+
+        fake_program(dir, &format!("{EXECUTABLE_PREFIX}fake"), &script)
+    }
+
+    fn test_package(package_name: &str) -> PackageData {
+        testing::test_package(
+            InstallationTools::Plugin("fake".to_string()),
+            package_name,
+            &[("channel", "stable")],
+        )
+    }
+
+    #[test]
+    fn test_plugin_installer() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_plugin(temp_dir.path(), PROTOCOL_VERSION);
+        let mut installer = PluginInstaller::new("fake").with_program(&program);
+
+        assert_eq!(
+            installer.get_type(),
+            InstallationTools::Plugin("fake".to_string())
+        );
+        assert_eq!(
+            installer.capabilities().unwrap(),
+            vec![PluginCapability::Search]
+        );
+
+        installer.install_package(&test_package("tool"))?;
+        installer.update_package(&test_package("tool"))?;
+        installer.delete_package(&test_package("tool"))?;
+
+        assert_eq!(
+            fs::read_to_string(program.with_extension("calls")).unwrap(),
+            "{\"id\":1,\"method\":\"install\",\"params\":{\"package\":{\"metadata\":{\"channel\":\"stable\"},\"package_name\":\"tool\"}}}\n\
+             {\"id\":2,\"method\":\"update\",\"params\":{\"package\":{\"metadata\":{\"channel\":\"stable\"},\"package_name\":\"tool\"}}}\n\
+             {\"id\":3,\"method\":\"delete\",\"params\":{\"package\":{\"metadata\":{\"channel\":\"stable\"},\"package_name\":\"tool\"}}}\n"
+        );
+
+        let packages = installer.get_packages()?;
+        assert_eq!(packages.len(), 1);
+        assert_eq!(packages[0].package_name, "tool");
+        assert_eq!(packages[0].metadata, test_package("tool").metadata);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plugin_errors() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_plugin(temp_dir.path(), PROTOCOL_VERSION);
+        let mut installer = PluginInstaller::new("fake").with_program(&program);
+
+        assert!(matches!(
+            installer.install_package(&test_package("broken")),
+            Err(ToolError::InstallingPackage(message)) if message.contains("broken package")
+        ));
+        assert_eq!(
+            installer.search("tool").unwrap()[0].package_name,
+            "tool-extra"
+        );
+        assert!(matches!(
+            installer.info("tool"),
+            Err(PluginError::MissingCapability(_, "info"))
+        ));
+    }
+
+    #[test]
+    fn test_hanging_plugin_times_out() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(
+            temp_dir.path(),
+            &format!("{EXECUTABLE_PREFIX}hanging"),
+            &format!(
+                "#!/bin/sh\nread -r line\necho '{{\"id\":0,\"result\":{{\"protocol_version\":{PROTOCOL_VERSION}}}}}'\nread -r line\nexec sleep 5\n"
+            ),
+        );
+        let mut installer = PluginInstaller::new("hanging").with_program(&program);
+        installer.set_run_control(RunControl {
+            timeout: Some(Duration::from_millis(100)),
+            ..RunControl::default()
+        });
+        let started = Instant::now();
+
+        assert!(matches!(
+            installer.install_package(&test_package("tool")),
+            Err(ToolError::TimedOut(name, _)) if name == "hanging"
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(
+            installer.get_packages(),
+            Err(ToolError::ListingPackages(_))
+        ));
+    }
+
+    #[test]
+    fn test_protocol_version_mismatch() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_plugin(temp_dir.path(), PROTOCOL_VERSION + 1);
+
+        assert!(matches!(
+            PluginInstaller::new("fake").with_program(&program).capabilities(),
+            Err(PluginError::UnsupportedVersion(_, version)) if version == PROTOCOL_VERSION + 1
+        ));
+
+        let missing = PluginInstaller::new("missing-plugin-for-tests");
+        assert!(!missing.is_available());
+        assert!(matches!(
+            missing.capabilities(),
+            Err(PluginError::Spawn(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_plugin_starts_on_first_use() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(
+            temp_dir.path(),
+            &format!("{EXECUTABLE_PREFIX}silent"),
+            "#!/bin/sh\necho started >> \"$0.calls\"\nexec sleep 5\n",
+        );
+        let mut installer = PluginInstaller::new("silent").with_program(&program);
+        installer.set_run_control(RunControl {
+            timeout: Some(Duration::from_millis(100)),
+            ..RunControl::default()
+        });
+
+        assert!(installer.is_available());
+        assert!(calls(&program).is_empty());
+
+        // The handshake never gets an answer
+        let started = Instant::now();
+        assert!(matches!(
+            installer.get_packages(),
+            Err(ToolError::TimedOut(name, _)) if name == "silent"
+        ));
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(calls(&program), vec!["started"]);
+    }
+
+    #[test]
+    fn test_info_keys_become_package_info() {
+        let metadata = PackageMetadata::from([
+            ("description".to_string(), "A tool".to_string()),
+            ("licenses".to_string(), "MIT, Apache-2.0".to_string()),
+            ("channel".to_string(), "stable".to_string()),
+        ]);
+
+        assert_eq!(
+            package_info(metadata),
+            PackageInfo {
+                description: Some("A tool".to_string()),
+                licenses: vec!["MIT".to_string(), "Apache-2.0".to_string()],
+                ..PackageInfo::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_discover_plugins() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fake_plugin(temp_dir.path(), PROTOCOL_VERSION);
+        fs::write(temp_dir.path().join("pazckager"), "").unwrap();
+        fs::create_dir(temp_dir.path().join(format!("{EXECUTABLE_PREFIX}dir"))).unwrap();
+
+        let plugin_names = discover_plugins_in(
+            [
+                temp_dir.path().to_path_buf(),
+                temp_dir.path().to_path_buf(),
+                temp_dir.path().join("missing"),
+            ]
+            .into_iter(),
+        );
+
+        assert_eq!(plugin_names, vec!["fake".to_string()]);
+    }
+}
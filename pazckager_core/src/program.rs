@@ -0,0 +1,512 @@
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use std::{env, io, thread};
+
+use crate::cancellation::CancellationToken;
+use crate::traits::{ToolError, ToolResult};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// Time a tool gets to clean up after SIGINT before its group is killed
+pub const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+// Looks the program up like the shell would, paths with a separator are used
+// as they are and bare names are searched in PATH
+pub fn find_program(program: impl AsRef<Path>) -> Option<PathBuf> {
+    let program = program.as_ref();
+
+    if program.components().count() > 1 {
+        return is_executable(program).then(|| program.to_path_buf());
+    }
+
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| is_executable(candidate))
+}
+
+fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+// Runs `program --version` and keeps the first line it prints
+pub fn program_version(program: impl AsRef<OsStr>) -> Option<String> {
+    let output = Command::new(program).arg("--version").output().ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+// Runs the command an installer lists its packages with and returns what it
+// printed, failing to run it is an error instead of an empty list. The listing
+// is stopped on cancellation and timeout like any other tool command
+pub fn listing_output(mut command: Command, control: &RunControl) -> ToolResult<String> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let output = control
+        .output(&mut command)
+        .map_err(|e| e.into_tool_error(&program, ToolError::ListingPackages))?;
+
+    if !output.status.success() {
+        return Err(ToolError::ListingPackages(format!(
+            "{program} failed to list packages with exit code: {}, {}",
+            output.status.code().unwrap_or(-1),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// How installers run the programs that change packages, shared by every
+// installer of a core so one token stops all of them
+#[derive(Debug, Clone, Default)]
+pub struct RunControl {
+    pub cancellation: CancellationToken,
+    pub timeout: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub enum RunError {
+    Spawn(io::Error),
+    Cancelled,
+    TimedOut(Duration),
+}
+
+impl RunError {
+    pub fn into_tool_error(self, program: &str, to_error: fn(String) -> ToolError) -> ToolError {
+        match self {
+            RunError::Spawn(e) => to_error(format!("Failed to execute {program}: {e}")),
+            RunError::Cancelled => ToolError::Cancelled(program.to_string()),
+            RunError::TimedOut(timeout) => {
+                ToolError::TimedOut(program.to_string(), timeout.as_secs())
+            }
+        }
+    }
+}
+
+impl RunControl {
+    // Runs the command in its own process group. With a terminal the group
+    // becomes the foreground one, so prompts keep working and Ctrl-C reaches
+    // the whole group, otherwise SIGINT is forwarded to it on cancellation.
+    // On timeout the group gets SIGINT too, and SIGKILL after a grace period
+    pub fn status(&self, command: &mut Command) -> Result<ExitStatus, RunError> {
+        self.wait(command, |_| ()).map(|(status, ())| status)
+    }
+
+    // Like status, with what the command prints captured. The pipes are read
+    // while it runs so a long listing can't fill them and block the tool
+    pub fn output(&self, command: &mut Command) -> Result<Output, RunError> {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+        let (status, (stdout, stderr)) = self.wait(command, |child| {
+            (read_all(child.stdout.take()), read_all(child.stderr.take()))
+        })?;
+
+        Ok(Output {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        })
+    }
+
+    fn wait<T>(
+        &self,
+        command: &mut Command,
+        on_spawn: impl FnOnce(&mut Child) -> T,
+    ) -> Result<(ExitStatus, T), RunError> {
+        if self.cancellation.is_cancelled() {
+            return Err(RunError::Cancelled);
+        }
+
+        let mut child = command.process_group(0).spawn().map_err(RunError::Spawn)?;
+        let spawned = on_spawn(&mut child);
+        let group = child.id() as libc::pid_t;
+        let foreground = ForegroundGroup::hand_to(group);
+        let started = Instant::now();
+
+        let result = loop {
+            if let Some(status) = child.try_wait().map_err(RunError::Spawn)? {
+                if is_interrupted(status) {
+                    self.cancellation.cancel();
+                    break Err(RunError::Cancelled);
+                }
+
+                break Ok(status);
+            }
+
+            let error = if self.cancellation.is_cancelled() {
+                Some(RunError::Cancelled)
+            } else {
+                self.timeout
+                    .filter(|timeout| started.elapsed() >= *timeout)
+                    .map(RunError::TimedOut)
+            };
+
+            if let Some(error) = error {
+                stop(&mut child, -group);
+                break Err(error);
+            }
+
+            thread::sleep(POLL_INTERVAL);
+        };
+
+        drop(foreground);
+        result.map(|status| (status, spawned))
+    }
+
+    // Runs the command an installer changes a package with, a non zero exit
+    // code is an error like failing to run it
+    pub fn run(
+        &self,
+        command: &mut Command,
+        tool: &str,
+        package_name: &str,
+        action: &str,
+        to_error: fn(String) -> ToolError,
+    ) -> ToolResult<()> {
+        let status = self
+            .status(command)
+            .map_err(|e| e.into_tool_error(tool, to_error))?;
+
+        if status.success() {
+            Ok(())
+        } else {
+            Err(to_error(format!(
+                "{tool} failed to {action} package {package_name} with exit code: {}",
+                status.code().unwrap_or(-1)
+            )))
+        }
+    }
+
+    // Waits for the next line a tool answering over a pipe prints, None once
+    // it closed its output. When cancelled or after the timeout the tool gets
+    // SIGINT, and SIGKILL after a grace period
+    pub fn read_line(
+        &self,
+        child: &mut Child,
+        lines: &OutputLines,
+    ) -> Result<Option<String>, RunError> {
+        let started = Instant::now();
+
+        loop {
+            let error = if self.cancellation.is_cancelled() {
+                Some(RunError::Cancelled)
+            } else {
+                self.timeout
+                    .filter(|timeout| started.elapsed() >= *timeout)
+                    .map(RunError::TimedOut)
+            };
+
+            if let Some(error) = error {
+                stop(child, child.id() as libc::pid_t);
+                return Err(error);
+            }
+
+            match lines.0.recv_timeout(POLL_INTERVAL) {
+                Ok(line) => return Ok(Some(line)),
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            }
+        }
+    }
+}
+
+// Lines of a tool output, read on their own thread so waiting for one can be
+// given up. Output that isn't valid UTF-8 ends the lines
+pub struct OutputLines(Receiver<String>);
+
+impl OutputLines {
+    pub fn new(output: impl Read + Send + 'static) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        thread::spawn(move || {
+            for line in BufReader::new(output).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Self(receiver)
+    }
+}
+
+// Reads a whole tool output on its own thread
+fn read_all(output: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut read = Vec::new();
+
+        if let Some(mut output) = output {
+            let _ = output.read_to_end(&mut read);
+        }
+
+        read
+    })
+}
+
+// Killed by Ctrl-C, or exited the way tools that catch SIGINT do
+fn is_interrupted(status: ExitStatus) -> bool {
+    status.signal() == Some(libc::SIGINT) || status.code() == Some(128 + libc::SIGINT)
+}
+
+// Stops the process, or the group when given its negated id
+fn stop(child: &mut Child, target: libc::pid_t) {
+    unsafe { libc::kill(target, libc::SIGINT) };
+
+    if !exits_within(child, GRACE_PERIOD) {
+        unsafe { libc::kill(target, libc::SIGKILL) };
+        let _ = child.wait();
+    }
+}
+
+// Waits for a tool that was told to exit, like by closing its stdin, and
+// kills it when it is still running after the grace period
+pub fn wait_or_kill(child: &mut Child, grace_period: Duration) {
+    if !exits_within(child, grace_period) {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+fn exits_within(child: &mut Child, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+
+    while Instant::now() < deadline {
+        if let Ok(Some(_)) = child.try_wait() {
+            return true;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    false
+}
+
+// Gives the terminal to a tool group while it runs, a background group that
+// reads a prompt would be stopped
+struct ForegroundGroup {
+    previous: libc::pid_t,
+}
+
+impl ForegroundGroup {
+    fn hand_to(group: libc::pid_t) -> Option<Self> {
+        unsafe {
+            if libc::isatty(libc::STDIN_FILENO) == 0
+                || libc::tcgetpgrp(libc::STDIN_FILENO) != libc::getpgrp()
+            {
+                return None;
+            }
+
+            let previous = libc::getpgrp();
+            set_foreground(group);
+            // The tool may have been stopped reading the terminal before it
+            // was handed over
+            libc::kill(-group, libc::SIGCONT);
+
+            Some(Self { previous })
+        }
+    }
+}
+
+impl Drop for ForegroundGroup {
+    fn drop(&mut self) {
+        set_foreground(self.previous);
+    }
+}
+
+// A background group changing the foreground one gets SIGTTOU
+fn set_foreground(group: libc::pid_t) {
+    unsafe {
+        let handler = libc::signal(libc::SIGTTOU, libc::SIG_IGN);
+        libc::tcsetpgrp(libc::STDIN_FILENO, group);
+        libc::signal(libc::SIGTTOU, handler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::process::Stdio;
+
+    #[test]
+    fn test_find_program() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = temp_dir.path().join("tool");
+        fs::write(&program, "#!/bin/sh\necho \"tool 1.2.3 $1\"\n").unwrap();
+
+        assert_eq!(find_program(&program), None);
+
+        fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(find_program(&program), Some(program.clone()));
+        assert_eq!(find_program(temp_dir.path().join("missing")), None);
+        assert_eq!(
+            program_version(&program),
+            Some("tool 1.2.3 --version".to_string())
+        );
+    }
+
+    #[test]
+    fn test_listing_output() {
+        let control = RunControl::default();
+        let mut failing = Command::new("sh");
+        failing.args(["-c", "echo broken >&2; exit 3"]);
+
+        assert_eq!(
+            listing_output(Command::new("echo"), &control).unwrap(),
+            "\n".to_string()
+        );
+        assert!(matches!(
+            listing_output(failing, &control),
+            Err(ToolError::ListingPackages(message)) if message == "sh failed to list packages with exit code: 3, broken"
+        ));
+        assert!(matches!(
+            listing_output(Command::new("/nonexistent/program"), &control),
+            Err(ToolError::ListingPackages(_))
+        ));
+    }
+
+    #[test]
+    fn test_listing_output_is_stopped() {
+        let control = RunControl {
+            cancellation: CancellationToken::new(),
+            timeout: Some(Duration::from_millis(200)),
+        };
+        let mut hanging = Command::new("sleep");
+        hanging.arg("10");
+
+        assert!(matches!(
+            listing_output(hanging, &control),
+            Err(ToolError::TimedOut(program, 0)) if program == "sleep"
+        ));
+
+        control.cancellation.cancel();
+
+        assert!(matches!(
+            listing_output(Command::new("echo"), &control),
+            Err(ToolError::Cancelled(program)) if program == "echo"
+        ));
+    }
+
+    #[test]
+    fn test_run_control() {
+        let control = RunControl {
+            cancellation: CancellationToken::new(),
+            timeout: Some(Duration::from_millis(200)),
+        };
+
+        assert!(control.status(&mut Command::new("true")).unwrap().success());
+        assert!(matches!(
+            control.status(Command::new("sh").args(["-c", "sleep 10"])),
+            Err(RunError::TimedOut(_))
+        ));
+
+        control.cancellation.cancel();
+
+        assert!(matches!(
+            control.status(&mut Command::new("true")),
+            Err(RunError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_run_fails_on_exit_code() {
+        let control = RunControl::default();
+
+        assert!(
+            control
+                .run(
+                    &mut Command::new("true"),
+                    "true",
+                    "ripgrep",
+                    "install",
+                    ToolError::InstallingPackage
+                )
+                .is_ok()
+        );
+        assert!(matches!(
+            control.run(
+                Command::new("sh").args(["-c", "exit 3"]),
+                "sh",
+                "ripgrep",
+                "remove",
+                ToolError::DeletingPackage
+            ),
+            Err(ToolError::DeletingPackage(message)) if message == "sh failed to remove package ripgrep with exit code: 3"
+        ));
+    }
+
+    #[test]
+    fn test_read_line() {
+        let control = RunControl {
+            cancellation: CancellationToken::new(),
+            timeout: Some(Duration::from_millis(200)),
+        };
+        let mut child = Command::new("sh")
+            .args(["-c", "echo ready; exec sleep 10"])
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let lines = OutputLines::new(child.stdout.take().unwrap());
+        let started = Instant::now();
+
+        assert_eq!(
+            control.read_line(&mut child, &lines).unwrap(),
+            Some("ready".to_string())
+        );
+        assert!(matches!(
+            control.read_line(&mut child, &lines),
+            Err(RunError::TimedOut(_))
+        ));
+        assert!(started.elapsed() < GRACE_PERIOD);
+        assert!(child.try_wait().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_wait_or_kill() {
+        let mut exiting = Command::new("true").spawn().unwrap();
+        let mut hanging = Command::new("sleep").arg("10").spawn().unwrap();
+        let started = Instant::now();
+
+        wait_or_kill(&mut exiting, Duration::from_millis(100));
+        wait_or_kill(&mut hanging, Duration::from_millis(100));
+
+        assert!(exiting.try_wait().unwrap().unwrap().success());
+        assert_eq!(
+            hanging.try_wait().unwrap().unwrap().signal(),
+            Some(libc::SIGKILL)
+        );
+        assert!(started.elapsed() < GRACE_PERIOD);
+    }
+
+    #[test]
+    fn test_cancelling_stops_the_running_program() {
+        let control = RunControl::default();
+        let cancellation = control.cancellation.clone();
+        let started = Instant::now();
+
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            cancellation.cancel();
+        });
+        let result = control.status(Command::new("sh").args(["-c", "sleep 10"]));
+        canceller.join().unwrap();
+
+        assert!(matches!(result, Err(RunError::Cancelled)));
+        assert!(started.elapsed() < GRACE_PERIOD);
+    }
+}
@@ -0,0 +1,376 @@
+// Storage contract shared by every `PazckagerStorage` backend. Each check
+// receives an empty store and panics when the backend does not conform.
+//
+// Backend crates run the whole suite with `storage_conformance_tests!`, giving
+// it the store variable and the statements that build an empty store in it:
+//
+// pazckager_core::storage_conformance_tests!(storage => {
+//     let temp_dir = tempfile::tempdir().unwrap();
+//     let mut storage = JsonPazckagerStorage::new(temp_dir.path().join("store.json").to_str().unwrap()).unwrap();
+// });
+
+use crate::models::{Category, InstallationTools, PackageData, PackageKey, PackageMetadata};
+use crate::traits::{PazckagerStorage, StoreError};
+
+fn category(category_name: &str) -> Category {
+    Category {
+        category_name: category_name.to_string(),
+        additional_info: Some(format!("{category_name} info")),
+    }
+}
+
+fn package(package_name: &str, category_name: &str) -> PackageData {
+    PackageData {
+        package_name: package_name.to_string(),
+        installation_tool: InstallationTools::Pacman,
+        instance: None,
+        category_name: category_name.to_string(),
+        installed: false,
+        metadata: PackageMetadata::new(),
+    }
+}
+
+fn key(package_name: &str) -> PackageKey {
+    PackageKey::new(InstallationTools::Pacman, package_name)
+}
+
+fn sorted<T: Ord>(mut values: Vec<T>) -> Vec<T> {
+    values.sort();
+    values
+}
+
+fn package_names(packages: Vec<PackageData>) -> Vec<String> {
+    sorted(packages.into_iter().map(|p| p.package_name).collect())
+}
+
+pub fn category_round_trip(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage
+        .store_category(Category {
+            category_name: "media".to_string(),
+            additional_info: None,
+        })
+        .unwrap();
+
+    assert!(storage.category_exists("dev").unwrap());
+    assert!(!storage.category_exists("games").unwrap());
+    assert_eq!(storage.get_category("dev").unwrap(), category("dev"));
+    assert_eq!(
+        sorted(
+            storage
+                .get_categories()
+                .unwrap()
+                .into_iter()
+                .map(|c| c.category_name)
+                .collect()
+        ),
+        vec!["dev".to_string(), "media".to_string()]
+    );
+}
+
+pub fn store_existing_category_fails(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+
+    let result = storage.store_category(Category {
+        category_name: "dev".to_string(),
+        additional_info: None,
+    });
+
+    assert!(matches!(result, Err(StoreError::AlreadyExists(_))));
+    assert_eq!(storage.get_category("dev").unwrap(), category("dev"));
+}
+
+pub fn get_missing_category_fails(storage: &mut impl PazckagerStorage) {
+    let result = storage.get_category("dev");
+
+    assert!(matches!(result, Err(StoreError::NotFound(_))));
+}
+
+pub fn remove_category(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+
+    storage.remove_category("dev").unwrap();
+
+    assert!(!storage.category_exists("dev").unwrap());
+    assert!(storage.get_categories().unwrap().is_empty());
+}
+
+pub fn remove_category_with_packages_fails(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_package(package("ripgrep", "dev")).unwrap();
+
+    let result = storage.remove_category("dev");
+
+    assert!(matches!(result, Err(StoreError::InUse(_))));
+    assert!(storage.category_exists("dev").unwrap());
+    assert!(storage.package_exists(&key("ripgrep")).unwrap());
+}
+
+pub fn remove_missing_category_fails(storage: &mut impl PazckagerStorage) {
+    let result = storage.remove_category("dev");
+
+    assert!(matches!(result, Err(StoreError::NotFound(_))));
+}
+
+pub fn package_round_trip(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_category(category("media")).unwrap();
+    storage.store_package(package("ripgrep", "dev")).unwrap();
+    storage.store_package(package("git", "dev")).unwrap();
+    storage.store_package(package("mpv", "media")).unwrap();
+
+    assert!(storage.package_exists(&key("ripgrep")).unwrap());
+    assert!(!storage.package_exists(&key("vim")).unwrap());
+    assert_eq!(
+        storage.get_package(&key("ripgrep")).unwrap(),
+        package("ripgrep", "dev")
+    );
+    assert_eq!(
+        package_names(storage.get_packages().unwrap()),
+        vec!["git".to_string(), "mpv".to_string(), "ripgrep".to_string()]
+    );
+    assert_eq!(
+        package_names(storage.get_packages_by_category("dev").unwrap()),
+        vec!["git".to_string(), "ripgrep".to_string()]
+    );
+}
+
+pub fn store_existing_package_fails(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_category(category("media")).unwrap();
+    storage.store_package(package("ripgrep", "dev")).unwrap();
+
+    let result = storage.store_package(package("ripgrep", "media"));
+
+    assert!(matches!(result, Err(StoreError::AlreadyExists(_))));
+    assert_eq!(
+        storage.get_package(&key("ripgrep")).unwrap(),
+        package("ripgrep", "dev")
+    );
+}
+
+pub fn store_package_in_missing_category_fails(storage: &mut impl PazckagerStorage) {
+    let result = storage.store_package(package("ripgrep", "dev"));
+
+    assert!(matches!(result, Err(StoreError::NotFound(_))));
+    assert!(!storage.package_exists(&key("ripgrep")).unwrap());
+}
+
+pub fn get_missing_package_fails(storage: &mut impl PazckagerStorage) {
+    let result = storage.get_package(&key("ripgrep"));
+
+    assert!(matches!(result, Err(StoreError::NotFound(_))));
+}
+
+pub fn update_package(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_category(category("media")).unwrap();
+    storage.store_package(package("ripgrep", "dev")).unwrap();
+
+    let updated_package = PackageData {
+        installed: true,
+        ..package("ripgrep", "media")
+    };
+    storage.update_package(updated_package.clone()).unwrap();
+
+    assert_eq!(
+        storage.get_package(&key("ripgrep")).unwrap(),
+        updated_package
+    );
+    assert!(storage.get_packages_by_category("dev").unwrap().is_empty());
+    assert_eq!(
+        storage.get_packages_by_category("media").unwrap(),
+        vec![updated_package]
+    );
+}
+
+pub fn update_missing_package_fails(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+
+    let result = storage.update_package(package("ripgrep", "dev"));
+
+    assert!(matches!(result, Err(StoreError::NotFound(_))));
+    assert!(!storage.package_exists(&key("ripgrep")).unwrap());
+}
+
+pub fn update_package_to_missing_category_fails(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_package(package("ripgrep", "dev")).unwrap();
+
+    let result = storage.update_package(package("ripgrep", "media"));
+
+    assert!(matches!(result, Err(StoreError::NotFound(_))));
+    assert_eq!(
+        storage.get_package(&key("ripgrep")).unwrap(),
+        package("ripgrep", "dev")
+    );
+}
+
+pub fn remove_package(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_package(package("ripgrep", "dev")).unwrap();
+
+    storage.remove_package(&key("ripgrep")).unwrap();
+
+    assert!(!storage.package_exists(&key("ripgrep")).unwrap());
+    assert!(storage.get_packages().unwrap().is_empty());
+}
+
+pub fn remove_missing_package_fails(storage: &mut impl PazckagerStorage) {
+    let result = storage.remove_package(&key("ripgrep"));
+
+    assert!(matches!(result, Err(StoreError::NotFound(_))));
+}
+
+pub fn same_name_for_different_tools(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_category(category("media")).unwrap();
+    storage.store_package(package("ripgrep", "dev")).unwrap();
+
+    let yay_package = PackageData {
+        installation_tool: InstallationTools::Yay,
+        ..package("ripgrep", "media")
+    };
+    storage.store_package(yay_package.clone()).unwrap();
+
+    assert_eq!(
+        storage.get_package(&key("ripgrep")).unwrap(),
+        package("ripgrep", "dev")
+    );
+    assert_eq!(
+        storage.get_package(&yay_package.key()).unwrap(),
+        yay_package
+    );
+    assert_eq!(storage.get_packages().unwrap().len(), 2);
+
+    storage.remove_package(&key("ripgrep")).unwrap();
+
+    assert!(!storage.package_exists(&key("ripgrep")).unwrap());
+    assert!(storage.package_exists(&yay_package.key()).unwrap());
+}
+
+pub fn same_name_for_different_instances(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_package(package("base", "dev")).unwrap();
+
+    let chroot_package = PackageData {
+        instance: Some("chroot".to_string()),
+        installed: true,
+        ..package("base", "dev")
+    };
+    storage.store_package(chroot_package.clone()).unwrap();
+
+    assert_eq!(
+        storage.get_package(&chroot_package.key()).unwrap(),
+        chroot_package
+    );
+    assert_eq!(storage.get_packages_by_name("base").unwrap().len(), 2);
+
+    storage.update_package(package("base", "dev")).unwrap();
+    storage.remove_package(&key("base")).unwrap();
+
+    assert!(!storage.package_exists(&key("base")).unwrap());
+    assert_eq!(storage.get_packages().unwrap(), vec![chroot_package]);
+}
+
+pub fn get_packages_by_name(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("dev")).unwrap();
+    storage.store_package(package("ripgrep", "dev")).unwrap();
+    storage.store_package(package("git", "dev")).unwrap();
+    storage
+        .store_package(PackageData {
+            installation_tool: InstallationTools::Yay,
+            ..package("ripgrep", "dev")
+        })
+        .unwrap();
+
+    let mut installation_tools: Vec<InstallationTools> = storage
+        .get_packages_by_name("ripgrep")
+        .unwrap()
+        .into_iter()
+        .map(|p| p.installation_tool)
+        .collect();
+    installation_tools.sort();
+
+    assert_eq!(
+        installation_tools,
+        vec![InstallationTools::Pacman, InstallationTools::Yay]
+    );
+    assert!(storage.get_packages_by_name("vim").unwrap().is_empty());
+}
+
+pub fn metadata_round_trip(storage: &mut impl PazckagerStorage) {
+    storage.store_category(category("apps")).unwrap();
+
+    let mut flatpak_package = PackageData {
+        installation_tool: InstallationTools::Flatpak,
+        metadata: PackageMetadata::from([("remote".to_string(), "flathub".to_string())]),
+        ..package("org.mozilla.firefox", "apps")
+    };
+    storage.store_package(flatpak_package.clone()).unwrap();
+
+    assert_eq!(
+        storage.get_package(&flatpak_package.key()).unwrap(),
+        flatpak_package
+    );
+
+    flatpak_package.metadata = PackageMetadata::from([
+        ("installation".to_string(), "system".to_string()),
+        ("remote".to_string(), "fedora".to_string()),
+    ]);
+    storage.update_package(flatpak_package.clone()).unwrap();
+
+    assert_eq!(
+        storage.get_packages_by_name("org.mozilla.firefox").unwrap(),
+        vec![flatpak_package]
+    );
+}
+
+pub fn packages_by_missing_category_is_empty(storage: &mut impl PazckagerStorage) {
+    let packages = storage.get_packages_by_category("dev").unwrap();
+
+    assert!(packages.is_empty());
+}
+
+#[macro_export]
+macro_rules! storage_conformance_tests {
+    ($storage:ident => $setup:tt) => {
+        mod storage_conformance {
+            use super::*;
+
+            $crate::storage_conformance_tests!(
+                @tests $storage => $setup;
+                category_round_trip,
+                store_existing_category_fails,
+                get_missing_category_fails,
+                remove_category,
+                remove_category_with_packages_fails,
+                remove_missing_category_fails,
+                package_round_trip,
+                store_existing_package_fails,
+                store_package_in_missing_category_fails,
+                get_missing_package_fails,
+                update_package,
+                update_missing_package_fails,
+                update_package_to_missing_category_fails,
+                remove_package,
+                remove_missing_package_fails,
+                same_name_for_different_tools,
+                same_name_for_different_instances,
+                get_packages_by_name,
+                metadata_round_trip,
+                packages_by_missing_category_is_empty,
+            );
+        }
+    };
+    (@tests $storage:ident => $setup:tt; $($check:ident,)*) => {
+        $($crate::storage_conformance_tests!(@test $check, $storage => $setup);)*
+    };
+    (@test $check:ident, $storage:ident => { $($setup:tt)* }) => {
+        #[test]
+        fn $check() {
+            $($setup)*
+            $crate::testing::conformance::$check(&mut $storage);
+        }
+    };
+}
@@ -0,0 +1,498 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{Arc, Mutex};
+
+use crate::models::{
+    Category, InstallationTools, PackageData, PackageInfo, PackageKey, PackageMetadata,
+    PackageUsage, RawPackageData,
+};
+use crate::program::RunControl;
+use crate::traits::{
+    InstallationTool, PazckagerStorage, StoreError, StoreResult, ToolCapability, ToolError,
+    ToolResult,
+};
+
+pub mod conformance;
+
+// Records its arguments next to itself and fails for the "broken" package
+pub const FAKE_TOOL: &str = "#!/bin/sh
+echo \"$@\" >> \"$0.calls\"
+case \"$*\" in
+    *broken*) exit 1 ;;
+esac
+";
+
+// Writes an executable script standing in for a tool an installer runs
+pub fn fake_program(dir: &Path, name: &str, script: &str) -> PathBuf {
+    let program = dir.join(name);
+    fs::write(&program, script).unwrap();
+    fs::set_permissions(&program, fs::Permissions::from_mode(0o755)).unwrap();
+    program
+}
+
+// What a fake program recorded in its ".calls" file, one line per call
+pub fn calls(program: &Path) -> Vec<String> {
+    fs::read_to_string(program.with_extension("calls"))
+        .unwrap_or_default()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+// The program and arguments of a command, to check it without running it
+pub fn command_line(command: &Command) -> Vec<String> {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| arg.to_string_lossy().to_string())
+        .collect()
+}
+
+// Checks an installer advertises what it does. Installer crates can't search,
+// the trait has nothing to search with, and an installer has Info exactly when
+// it describes the package, which the tool it runs must know
+pub fn check_capabilities(installer: &dyn InstallationTool, package: &PackageData) {
+    let capabilities = installer.capabilities();
+
+    assert!(!capabilities.contains(&ToolCapability::Search));
+    assert_eq!(
+        capabilities.contains(&ToolCapability::Info),
+        matches!(installer.info(package), Ok(Some(_)))
+    );
+}
+
+pub fn test_package(
+    installation_tool: InstallationTools,
+    package_name: &str,
+    metadata: &[(&str, &str)],
+) -> PackageData {
+    PackageData {
+        package_name: package_name.to_string(),
+        installation_tool,
+        instance: None,
+        category_name: "default".to_string(),
+        installed: false,
+        metadata: metadata
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+#[derive(Default, Clone, Debug)]
+pub struct MemoryStorage {
+    categories: BTreeMap<String, Category>,
+    packages: BTreeMap<PackageKey, PackageData>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl MemoryStorage {
+    fn ensure_category_exists(&self, category_name: &str) -> StoreResult<()> {
+        if !self.categories.contains_key(category_name) {
+            return Err(StoreError::NotFound(format!("Category {category_name}")));
+        }
+
+        Ok(())
+    }
+}
+
+impl PazckagerStorage for MemoryStorage {
+    fn store_category(&mut self, category: Category) -> StoreResult<()> {
+        if self.categories.contains_key(&category.category_name) {
+            return Err(StoreError::AlreadyExists(format!(
+                "Category {}",
+                category.category_name
+            )));
+        }
+
+        self.categories
+            .insert(category.category_name.clone(), category);
+
+        Ok(())
+    }
+
+    fn get_categories(&self) -> StoreResult<Vec<Category>> {
+        Ok(self.categories.values().cloned().collect())
+    }
+
+    fn get_category(&self, category_name: &str) -> StoreResult<Category> {
+        self.categories
+            .get(category_name)
+            .cloned()
+            .ok_or(StoreError::NotFound(format!("Category {category_name}")))
+    }
+
+    fn category_exists(&self, category_name: &str) -> StoreResult<bool> {
+        Ok(self.categories.contains_key(category_name))
+    }
+
+    fn remove_category(&mut self, category_name: &str) -> StoreResult<()> {
+        if self
+            .packages
+            .values()
+            .any(|package| package.category_name == category_name)
+        {
+            return Err(StoreError::InUse(format!("Category {category_name}")));
+        }
+
+        self.categories
+            .remove(category_name)
+            .ok_or(StoreError::NotFound(format!("Category {category_name}")))?;
+
+        Ok(())
+    }
+
+    fn store_package(&mut self, package: PackageData) -> StoreResult<()> {
+        let package_key = package.key();
+
+        if self.packages.contains_key(&package_key) {
+            return Err(StoreError::AlreadyExists(format!("Package {package_key}")));
+        }
+
+        self.ensure_category_exists(&package.category_name)?;
+        self.packages.insert(package_key, package);
+
+        Ok(())
+    }
+
+    fn update_package(&mut self, package: PackageData) -> StoreResult<()> {
+        self.ensure_category_exists(&package.category_name)?;
+
+        let package_key = package.key();
+        let package_mut = self
+            .packages
+            .get_mut(&package_key)
+            .ok_or(StoreError::NotFound(format!("Package {package_key}")))?;
+
+        *package_mut = package;
+
+        Ok(())
+    }
+
+    fn get_packages(&self) -> StoreResult<Vec<PackageData>> {
+        Ok(self.packages.values().cloned().collect())
+    }
+
+    fn package_exists(&self, package_key: &PackageKey) -> StoreResult<bool> {
+        Ok(self.packages.contains_key(package_key))
+    }
+
+    fn get_package(&self, package_key: &PackageKey) -> StoreResult<PackageData> {
+        self.packages
+            .get(package_key)
+            .cloned()
+            .ok_or(StoreError::NotFound(format!("Package {package_key}")))
+    }
+
+    fn get_packages_by_name(&self, package_name: &str) -> StoreResult<Vec<PackageData>> {
+        Ok(self
+            .packages
+            .values()
+            .filter(|p| p.package_name == package_name)
+            .cloned()
+            .collect())
+    }
+
+    fn get_packages_by_category(&self, category_name: &str) -> StoreResult<Vec<PackageData>> {
+        Ok(self
+            .packages
+            .values()
+            .filter(|p| p.category_name == category_name)
+            .cloned()
+            .collect())
+    }
+
+    fn remove_package(&mut self, package_key: &PackageKey) -> StoreResult<()> {
+        self.packages
+            .remove(package_key)
+            .ok_or(StoreError::NotFound(format!("Package {package_key}")))?;
+
+        Ok(())
+    }
+}
+
+// Simulates an installation tool with an in memory set of installed packages.
+// Clones share the same set, so a test can keep a clone to inspect the state
+// after handing the installer to the core.
+#[derive(Clone, Debug)]
+pub struct FakeInstaller {
+    installation_tool: InstallationTools,
+    installed_packages: Arc<Mutex<BTreeSet<String>>>,
+    failing_packages: Arc<Mutex<BTreeSet<String>>>,
+    interrupted_packages: Arc<Mutex<BTreeSet<String>>>,
+    batch_calls: Arc<Mutex<Vec<&'static str>>>,
+    resolved_metadata: PackageMetadata,
+    info: Option<PackageInfo>,
+    disk_usage: Vec<PackageUsage>,
+    available: bool,
+    failing_listing: bool,
+    control: RunControl,
+}
+
+impl FakeInstaller {
+    pub fn new(installation_tool: InstallationTools) -> Self {
+        Self {
+            installation_tool,
+            installed_packages: Arc::default(),
+            failing_packages: Arc::default(),
+            interrupted_packages: Arc::default(),
+            batch_calls: Arc::default(),
+            resolved_metadata: PackageMetadata::new(),
+            info: None,
+            disk_usage: Vec::new(),
+            available: true,
+            failing_listing: false,
+            control: RunControl::default(),
+        }
+    }
+
+    // Simulates a tool that fails to list its installed packages
+    pub fn failing_listing(mut self) -> Self {
+        self.failing_listing = true;
+        self
+    }
+
+    // Simulates a tool whose program is not installed
+    pub fn unavailable(mut self) -> Self {
+        self.available = false;
+        self
+    }
+
+    pub fn with_installed<I, S>(self, package_names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.installed_packages
+            .lock()
+            .unwrap()
+            .extend(package_names.into_iter().map(Into::into));
+
+        self
+    }
+
+    // Metadata added to every package it installs, like a tool choosing a
+    // provider while installing
+    pub fn resolving(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.resolved_metadata.insert(key.into(), value.into());
+        self
+    }
+
+    // Information returned for every package
+    pub fn describing(mut self, info: PackageInfo) -> Self {
+        self.info = Some(info);
+        self
+    }
+
+    // Size and dependencies reported for a package
+    pub fn using_disk(mut self, package_name: &str, size: u64, dependencies: &[&str]) -> Self {
+        self.disk_usage.push(PackageUsage {
+            package_name: package_name.to_string(),
+            size,
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+        });
+        self
+    }
+
+    pub fn failing_on(self, package_name: impl Into<String>) -> Self {
+        self.failing_packages
+            .lock()
+            .unwrap()
+            .insert(package_name.into());
+
+        self
+    }
+
+    // Simulates a tool interrupted by Ctrl-C after installing or removing the
+    // package but before it reported success, the token gets cancelled
+    pub fn interrupted_on(self, package_name: impl Into<String>) -> Self {
+        self.interrupted_packages
+            .lock()
+            .unwrap()
+            .insert(package_name.into());
+
+        self
+    }
+
+    pub fn is_installed(&self, package_name: &str) -> bool {
+        self.installed_packages
+            .lock()
+            .unwrap()
+            .contains(package_name)
+    }
+
+    pub fn installed_packages(&self) -> Vec<String> {
+        self.installed_packages
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    // "begin" and "end" for every batch the core ran
+    pub fn batch_calls(&self) -> Vec<&'static str> {
+        self.batch_calls.lock().unwrap().clone()
+    }
+
+    fn is_failing(&self, package_name: &str) -> bool {
+        self.failing_packages.lock().unwrap().contains(package_name)
+    }
+}
+
+impl InstallationTool for FakeInstaller {
+    fn get_type(&self) -> InstallationTools {
+        self.installation_tool.clone()
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let package_name = &package.package_name;
+
+        if self.is_failing(package_name) {
+            return Err(ToolError::InstallingPackage(format!(
+                "Simulated failure installing {package_name}"
+            )));
+        }
+
+        self.installed_packages
+            .lock()
+            .unwrap()
+            .insert(self.installed_name(package).to_string());
+
+        if self
+            .interrupted_packages
+            .lock()
+            .unwrap()
+            .contains(package_name)
+        {
+            self.control.cancellation.cancel();
+            return Err(ToolError::Cancelled(package_name.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let package_name = &package.package_name;
+
+        if self.is_failing(package_name) || !self.is_installed(self.installed_name(package)) {
+            return Err(ToolError::DeletingPackage(format!(
+                "Package {package_name} is not installed"
+            )));
+        }
+
+        self.installed_packages
+            .lock()
+            .unwrap()
+            .remove(self.installed_name(package));
+
+        if self
+            .interrupted_packages
+            .lock()
+            .unwrap()
+            .contains(package_name)
+        {
+            self.control.cancellation.cancel();
+            return Err(ToolError::Cancelled(package_name.clone()));
+        }
+
+        Ok(())
+    }
+
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let package_name = &package.package_name;
+
+        if self.is_failing(package_name) || !self.is_installed(self.installed_name(package)) {
+            return Err(ToolError::UpdatingPackage(format!(
+                "Package {package_name} is not installed"
+            )));
+        }
+
+        Ok(())
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        self.available
+    }
+
+    fn version(&self) -> Option<String> {
+        Some("fake 1.0.0".to_string())
+    }
+
+    fn installed_metadata(&self, package: &PackageData) -> PackageMetadata {
+        let mut metadata = self.resolved_metadata.clone();
+        metadata.extend(package.metadata.clone());
+        metadata
+    }
+
+    // A recorded provider is installed and listed instead, like pacman does
+    // with virtual packages
+    fn installed_name<'a>(&self, package: &'a PackageData) -> &'a str {
+        package
+            .metadata
+            .get("provider")
+            .unwrap_or(&package.package_name)
+    }
+
+    fn begin_batch(&mut self) -> ToolResult<()> {
+        self.batch_calls.lock().unwrap().push("begin");
+        Ok(())
+    }
+
+    fn end_batch(&mut self) -> ToolResult<()> {
+        self.batch_calls.lock().unwrap().push("end");
+        Ok(())
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        // A cancelled listing is stopped like any other tool command
+        if self.control.cancellation.is_cancelled() {
+            return Err(ToolError::Cancelled(self.installation_tool.to_string()));
+        }
+
+        if self.failing_listing {
+            return Err(ToolError::ListingPackages(
+                "Simulated failure listing packages".to_string(),
+            ));
+        }
+
+        Ok(self
+            .installed_packages()
+            .into_iter()
+            .map(|package_name| RawPackageData {
+                package_name,
+                installation_tool: self.installation_tool.clone(),
+                category_name: String::new(),
+                metadata: PackageMetadata::new(),
+            })
+            .collect())
+    }
+
+    fn info(&self, _package: &PackageData) -> ToolResult<Option<PackageInfo>> {
+        Ok(self.info.clone())
+    }
+
+    fn disk_usage(&self) -> ToolResult<Vec<PackageUsage>> {
+        Ok(self.disk_usage.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    crate::storage_conformance_tests!(storage => {
+        let mut storage = MemoryStorage::new();
+    });
+}
@@ -1,6 +1,13 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
 use thiserror::Error;
 
-use crate::models::{Category, InstallationTools, PackageData, RawPackageData};
+use crate::models::{
+    Category, InstallationTools, InstallerId, PackageData, PackageInfo, PackageKey,
+    PackageMetadata, PackageUsage, RawPackageData,
+};
+use crate::program::RunControl;
 
 pub trait PazckagerStorage {
     fn store_category(&mut self, category: Category) -> StoreResult<()>;
@@ -19,13 +26,15 @@ pub trait PazckagerStorage {
 
     fn get_packages(&self) -> StoreResult<Vec<PackageData>>;
 
-    fn package_exists(&self, package_name: &str) -> StoreResult<bool>;
+    fn package_exists(&self, package_key: &PackageKey) -> StoreResult<bool>;
+
+    fn get_package(&self, package_key: &PackageKey) -> StoreResult<PackageData>;
 
-    fn get_package(&self, package_name: &str) -> StoreResult<PackageData>;
+    fn get_packages_by_name(&self, package_name: &str) -> StoreResult<Vec<PackageData>>;
 
     fn get_packages_by_category(&self, category_name: &str) -> StoreResult<Vec<PackageData>>;
 
-    fn remove_package(&mut self, package_name: &str) -> StoreResult<()>;
+    fn remove_package(&mut self, package_key: &PackageKey) -> StoreResult<()>;
 }
 
 pub type StoreResult<T> = Result<T, StoreError>;
@@ -34,18 +43,128 @@ pub type StoreResult<T> = Result<T, StoreError>;
 pub enum StoreError {
     #[error("{0}")]
     InternalStoreError(String),
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("{0} already exists")]
+    AlreadyExists(String),
+    #[error("{0} is still in use")]
+    InUse(String),
 }
 
 pub trait InstallationTool {
     fn get_type(&self) -> InstallationTools;
 
-    fn install_package(&mut self, package_name: &str) -> ToolResult<()>;
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()>;
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()>;
+
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()>;
+
+    // Failing to list must be reported, an empty list means that nothing is
+    // installed
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>>;
+
+    // Checked when the installer is registered, installers whose program is
+    // missing are skipped instead of failing on their first command
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn version(&self) -> Option<String> {
+        None
+    }
+
+    fn capabilities(&self) -> BTreeSet<ToolCapability> {
+        BTreeSet::new()
+    }
+
+    // Tools with the Info capability describe the package, None when the tool
+    // doesn't know it
+    fn info(&self, _package: &PackageData) -> ToolResult<Option<PackageInfo>> {
+        Ok(None)
+    }
+
+    // Sizes and dependencies of every package the tool installed, tools that
+    // can't tell return nothing and their packages count without a size
+    fn disk_usage(&self) -> ToolResult<Vec<PackageUsage>> {
+        Ok(Vec::new())
+    }
+
+    // Metadata stored once the package is installed. Tools that make choices
+    // while installing, like the provider of a virtual package, record them
+    // here so later installs on other machines make the same ones
+    fn installed_metadata(&self, package: &PackageData) -> PackageMetadata {
+        package.metadata.clone()
+    }
+
+    // Name the tool installs and lists the package under, like the recorded
+    // provider of a virtual package
+    fn installed_name<'a>(&self, package: &'a PackageData) -> &'a str {
+        &package.package_name
+    }
+
+    // Tools that run programs use it to stop them when the operation is
+    // cancelled or takes too long
+    fn set_run_control(&mut self, _control: RunControl) {}
+
+    // Called around operations on several packages, like installing a
+    // category, so tools can authenticate once for the whole batch. Every
+    // begin_batch is followed by end_batch, even when the operation fails
+    fn begin_batch(&mut self) -> ToolResult<()> {
+        Ok(())
+    }
+
+    fn end_batch(&mut self) -> ToolResult<()> {
+        Ok(())
+    }
+}
 
-    fn delete_package(&mut self, package_name: &str) -> ToolResult<()>;
+// What an installation tool supports besides installing, removing, updating
+// and listing packages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ToolCapability {
+    // Runs the packages of a batch in one session, see begin_batch
+    Batch,
+    // Only plugins can search, through PluginInstaller::search
+    Search,
+    Info,
+    // Pinning packages to a version
+    Versions,
+    // Installing without root privileges
+    UserScope,
+}
 
-    fn update_package(&mut self, package_name: &str) -> ToolResult<()>;
+impl Display for ToolCapability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ToolCapability::Batch => write!(f, "batch"),
+            ToolCapability::Search => write!(f, "search"),
+            ToolCapability::Info => write!(f, "info"),
+            ToolCapability::Versions => write!(f, "versions"),
+            ToolCapability::UserScope => write!(f, "user scope"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolStatus {
+    pub installer: InstallerId,
+    pub available: bool,
+    pub version: Option<String>,
+    pub capabilities: BTreeSet<ToolCapability>,
+}
 
-    fn get_packages(&self) -> Vec<RawPackageData>;
+impl ToolStatus {
+    pub fn of(installer: InstallerId, installation_tool: &dyn InstallationTool) -> Self {
+        let available = installation_tool.is_available();
+
+        Self {
+            installer,
+            available,
+            version: available.then(|| installation_tool.version()).flatten(),
+            capabilities: installation_tool.capabilities(),
+        }
+    }
 }
 
 pub type ToolResult<T> = Result<T, ToolError>;
@@ -58,4 +177,14 @@ pub enum ToolError {
     DeletingPackage(String),
     #[error("Error updating package: {0}")]
     UpdatingPackage(String),
+    #[error("Error listing packages: {0}")]
+    ListingPackages(String),
+    #[error("Error querying package: {0}")]
+    QueryingPackage(String),
+    #[error("Error starting privileged session: {0}")]
+    StartingSession(String),
+    #[error("{0} was cancelled")]
+    Cancelled(String),
+    #[error("{0} timed out after {1} seconds")]
+    TimedOut(String, u64),
 }
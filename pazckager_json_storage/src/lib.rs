@@ -2,18 +2,31 @@ use pazckager_core::traits::{StoreError, StoreResult};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::{self, File};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use models::{Category, PackageData};
+use models::{Category, PackageData, PackageKey};
 use pazckager_core::*;
 use traits::{PazckagerStorage, StoreError as Error};
 
+// Packages are keyed by `PackageKey::to_string`, stores written before
+// packages were identified by tool plus name are keyed by name only and get
+// re-keyed when loaded
 #[derive(Serialize, Deserialize, Default)]
 struct JsonStore {
     categories: HashMap<String, Category>,
     packages: HashMap<String, PackageData>,
 }
 
+impl JsonStore {
+    fn rekey_packages(&mut self) {
+        self.packages = self
+            .packages
+            .drain()
+            .map(|(_, package)| (package.key().to_string(), package))
+            .collect();
+    }
+}
+
 pub struct JsonPazckagerStorage {
     store: JsonStore,
     file_path: PathBuf,
@@ -31,13 +44,27 @@ impl JsonPazckagerStorage {
         let store = if file_path.exists() {
             let file =
                 File::open(&file_path).map_err(|e| Error::InternalStoreError(e.to_string()))?;
-            serde_json::from_reader(file).map_err(|e| Error::InternalStoreError(e.to_string()))?
+            let mut store: JsonStore = serde_json::from_reader(file)
+                .map_err(|e| Error::InternalStoreError(e.to_string()))?;
+            store.rekey_packages();
+            store
         } else {
             JsonStore::default()
         };
         Ok(Self { store, file_path })
     }
 
+    // Opens a store that has to exist already, like one being migrated
+    pub fn open(file_path: &str) -> StoreResult<Self> {
+        let expanded_path = shellexpand::tilde(&file_path).to_string();
+
+        if !Path::new(&expanded_path).is_file() {
+            return Err(Error::NotFound(format!("Json store {expanded_path}")));
+        }
+
+        Self::new(file_path)
+    }
+
     fn save_to_file(&self) -> StoreResult<()> {
         if self.file_path.exists() {
             fs::remove_file(&self.file_path)
@@ -49,11 +76,24 @@ impl JsonPazckagerStorage {
             .map_err(|e| StoreError::InternalStoreError(e.to_string()))?;
         Ok(())
     }
+
+    fn ensure_category_exists(&self, category_name: &str) -> StoreResult<()> {
+        if !self.store.categories.contains_key(category_name) {
+            return Err(Error::NotFound(format!("Category {category_name}")));
+        }
+
+        Ok(())
+    }
 }
 
 impl PazckagerStorage for JsonPazckagerStorage {
     fn store_category(&mut self, category: Category) -> StoreResult<()> {
         let category_name = category.category_name.clone();
+
+        if self.store.categories.contains_key(&category_name) {
+            return Err(Error::AlreadyExists(format!("Category {category_name}")));
+        }
+
         self.store.categories.insert(category_name, category);
         self.save_to_file()?;
         Ok(())
@@ -68,14 +108,32 @@ impl PazckagerStorage for JsonPazckagerStorage {
     }
 
     fn remove_category(&mut self, category_name: &str) -> StoreResult<()> {
-        self.store.categories.remove(category_name);
+        if self
+            .store
+            .packages
+            .values()
+            .any(|package| package.category_name == category_name)
+        {
+            return Err(Error::InUse(format!("Category {category_name}")));
+        }
+
+        self.store
+            .categories
+            .remove(category_name)
+            .ok_or(Error::NotFound(format!("Category {category_name}")))?;
         self.save_to_file()?;
         Ok(())
     }
 
     fn store_package(&mut self, package: PackageData) -> StoreResult<()> {
-        let package_name = package.package_name.clone();
-        self.store.packages.insert(package_name, package);
+        let package_key = package.key().to_string();
+
+        if self.store.packages.contains_key(&package_key) {
+            return Err(Error::AlreadyExists(format!("Package {package_key}")));
+        }
+
+        self.ensure_category_exists(&package.category_name)?;
+        self.store.packages.insert(package_key, package);
         self.save_to_file()?;
         Ok(())
     }
@@ -84,16 +142,26 @@ impl PazckagerStorage for JsonPazckagerStorage {
         Ok(self.store.packages.values().cloned().collect())
     }
 
-    fn package_exists(&self, package_name: &str) -> StoreResult<bool> {
-        Ok(self.store.packages.contains_key(package_name))
+    fn package_exists(&self, package_key: &PackageKey) -> StoreResult<bool> {
+        Ok(self.store.packages.contains_key(&package_key.to_string()))
     }
 
-    fn get_package(&self, package_name: &str) -> StoreResult<PackageData> {
+    fn get_package(&self, package_key: &PackageKey) -> StoreResult<PackageData> {
         self.store
             .packages
-            .get(package_name)
+            .get(&package_key.to_string())
             .cloned()
-            .ok_or(Error::InternalStoreError("Package not found".to_string()))
+            .ok_or(Error::NotFound(format!("Package {package_key}")))
+    }
+
+    fn get_packages_by_name(&self, package_name: &str) -> StoreResult<Vec<PackageData>> {
+        Ok(self
+            .store
+            .packages
+            .values()
+            .filter(|p| p.package_name == package_name)
+            .cloned()
+            .collect())
     }
 
     fn get_packages_by_category(&self, category_name: &str) -> StoreResult<Vec<PackageData>> {
@@ -106,13 +174,11 @@ impl PazckagerStorage for JsonPazckagerStorage {
             .collect())
     }
 
-    fn remove_package(&mut self, package_name: &str) -> StoreResult<()> {
+    fn remove_package(&mut self, package_key: &PackageKey) -> StoreResult<()> {
         self.store
             .packages
-            .remove(package_name)
-            .ok_or(StoreError::InternalStoreError(
-                "Package does not exists".to_string(),
-            ))?;
+            .remove(&package_key.to_string())
+            .ok_or(Error::NotFound(format!("Package {package_key}")))?;
 
         self.save_to_file()?;
 
@@ -120,13 +186,18 @@ impl PazckagerStorage for JsonPazckagerStorage {
     }
 
     fn update_package(&mut self, package: PackageData) -> StoreResult<()> {
-        let package_mut = self.store.packages.get_mut(&package.package_name).ok_or(
-            StoreError::InternalStoreError("Package does not exists".to_string()),
-        )?;
+        self.ensure_category_exists(&package.category_name)?;
+
+        let package_mut = self
+            .store
+            .packages
+            .get_mut(&package.key().to_string())
+            .ok_or(Error::NotFound(format!("Package {}", package.key())))?;
 
         package_mut.category_name = package.category_name;
         package_mut.installation_tool = package.installation_tool;
         package_mut.installed = package.installed;
+        package_mut.metadata = package.metadata;
 
         self.save_to_file()?;
 
@@ -134,13 +205,11 @@ impl PazckagerStorage for JsonPazckagerStorage {
     }
 
     fn get_category(&self, category_name: &str) -> StoreResult<Category> {
-        let category =
-            self.store
-                .categories
-                .get(category_name)
-                .ok_or(StoreError::InternalStoreError(
-                    "Category does not exist".to_string(),
-                ))?;
+        let category = self
+            .store
+            .categories
+            .get(category_name)
+            .ok_or(Error::NotFound(format!("Category {category_name}")))?;
 
         Ok(category.clone())
     }
@@ -151,12 +220,18 @@ mod tests {
     use pazckager_core::models::InstallationTools;
 
     use super::*;
-    use std::fs;
+
+    pazckager_core::storage_conformance_tests!(storage => {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().join("test_store.json");
+        let mut storage = JsonPazckagerStorage::new(store_path.to_str().unwrap()).unwrap();
+    });
 
     #[test]
     fn test_json_storage() -> StoreResult<()> {
-        let temp_file = "test_store.json";
-        let mut storage = JsonPazckagerStorage::new(temp_file)?;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("test_store.json");
+        let mut storage = JsonPazckagerStorage::new(temp_file.to_str().unwrap())?;
 
         let category = Category {
             category_name: "test".to_string(),
@@ -171,16 +246,60 @@ mod tests {
         let package = PackageData {
             package_name: "test_pkg".to_string(),
             installation_tool: InstallationTools::Pacman,
+            instance: None,
             category_name: "test".to_string(),
             installed: true,
+            metadata: Default::default(),
         };
         storage.store_package(package.clone())?;
-        assert!(storage.package_exists("test_pkg")?);
+        assert!(storage.package_exists(&package.key())?);
         let packages = storage.get_packages()?;
         assert_eq!(packages.len(), 1);
         assert_eq!(packages[0], package);
 
-        fs::remove_file(temp_file).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn test_open_requires_existing_store() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("missing.json");
+
+        assert!(matches!(
+            JsonPazckagerStorage::open(temp_file.to_str().unwrap()),
+            Err(StoreError::NotFound(_))
+        ));
+        assert!(!temp_file.exists());
+    }
+
+    #[test]
+    fn test_name_keyed_store_is_migrated() -> StoreResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_file = temp_dir.path().join("test_store.json");
+        fs::write(
+            &temp_file,
+            r#"{
+                "categories": { "default": { "category_name": "default", "additional_info": null } },
+                "packages": {
+                    "ripgrep": {
+                        "package_name": "ripgrep",
+                        "installation_tool": "Pacman",
+                        "category_name": "default",
+                        "installed": true
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let mut storage = JsonPazckagerStorage::new(temp_file.to_str().unwrap())?;
+        let package_key = PackageKey::new(InstallationTools::Pacman, "ripgrep");
+
+        assert!(storage.get_package(&package_key)?.installed);
+
+        storage.remove_package(&package_key)?;
+        assert!(storage.get_packages()?.is_empty());
+
         Ok(())
     }
 }
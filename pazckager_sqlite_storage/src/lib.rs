@@ -0,0 +1,664 @@
+use pazckager_core::models::{
+    Category, InstallationTools, PackageData, PackageKey, PackageMetadata,
+};
+use pazckager_core::traits::{PazckagerStorage, StoreError, StoreResult};
+use rusqlite::{Connection, OptionalExtension, Row, params};
+use std::fs;
+use std::path::PathBuf;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS categories (
+        category_name TEXT PRIMARY KEY NOT NULL,
+        additional_info TEXT
+    );
+
+    CREATE TABLE IF NOT EXISTS packages (
+        package_name TEXT NOT NULL,
+        installation_tool TEXT NOT NULL,
+        instance TEXT NOT NULL DEFAULT '',
+        category_name TEXT NOT NULL REFERENCES categories (category_name),
+        installed INTEGER NOT NULL,
+        metadata TEXT NOT NULL DEFAULT '{}',
+        PRIMARY KEY (package_name, installation_tool, instance)
+    );
+
+    CREATE INDEX IF NOT EXISTS packages_category_name_idx ON packages (category_name);
+    CREATE INDEX IF NOT EXISTS packages_installation_tool_idx ON packages (installation_tool);
+";
+
+const SCHEMA_VERSION: i32 = 3;
+
+// Older packages tables are rebuilt, version 0 keyed packages by package_name
+// only, version 1 had no package metadata and version 2 had no installer
+// instance. The default instance is stored as an empty instance.
+const RENAME_OLD_PACKAGES: &str = "
+    DROP INDEX IF EXISTS packages_category_name_idx;
+    DROP INDEX IF EXISTS packages_installation_tool_idx;
+    ALTER TABLE packages RENAME TO packages_old;
+";
+
+const VERSION_0_COLUMNS: &str = "package_name, installation_tool, category_name, installed";
+const VERSION_2_COLUMNS: &str =
+    "package_name, installation_tool, category_name, installed, metadata";
+
+pub struct SqlitePazckagerStorage {
+    connection: Connection,
+}
+
+impl SqlitePazckagerStorage {
+    pub fn new(file_path: &str) -> StoreResult<Self> {
+        let expanded_path = shellexpand::tilde(&file_path).to_string();
+        let file_path = PathBuf::from(expanded_path);
+
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| StoreError::InternalStoreError(e.to_string()))?;
+        }
+
+        let connection = Connection::open(&file_path).map_err(to_store_error)?;
+
+        Self::from_connection(connection)
+    }
+
+    pub fn in_memory() -> StoreResult<Self> {
+        let connection = Connection::open_in_memory().map_err(to_store_error)?;
+
+        Self::from_connection(connection)
+    }
+
+    fn from_connection(mut connection: Connection) -> StoreResult<Self> {
+        connection
+            .pragma_update(None, "foreign_keys", true)
+            .map_err(to_store_error)?;
+
+        migrate(&mut connection).map_err(to_store_error)?;
+
+        Ok(Self { connection })
+    }
+
+    pub fn import_from(&mut self, source: &impl PazckagerStorage) -> StoreResult<()> {
+        let categories = source.get_categories()?;
+        let packages = source.get_packages()?;
+
+        let transaction = self.connection.transaction().map_err(to_store_error)?;
+
+        for category in categories {
+            upsert_category(&transaction, &category)?;
+        }
+
+        for package in packages {
+            transaction
+                .execute(
+                    "INSERT OR IGNORE INTO categories (category_name, additional_info) VALUES (?1, NULL)",
+                    params![package.category_name],
+                )
+                .map_err(to_store_error)?;
+            upsert_package(&transaction, &package)?;
+        }
+
+        transaction.commit().map_err(to_store_error)?;
+
+        Ok(())
+    }
+
+    fn query_packages(
+        &self,
+        query: &str,
+        params: impl rusqlite::Params,
+    ) -> StoreResult<Vec<PackageData>> {
+        let mut statement = self.connection.prepare(query).map_err(to_store_error)?;
+
+        statement
+            .query_map(params, package_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(to_store_error)
+    }
+
+    fn ensure_category_exists(&self, category_name: &str) -> StoreResult<()> {
+        if !self.category_exists(category_name)? {
+            return Err(StoreError::NotFound(format!("Category {category_name}")));
+        }
+
+        Ok(())
+    }
+}
+
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let version: i32 = connection.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+    if version >= SCHEMA_VERSION {
+        return Ok(());
+    }
+
+    let transaction = connection.transaction()?;
+
+    let has_old_packages: bool = transaction.query_row(
+        "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'packages')",
+        [],
+        |row| row.get(0),
+    )?;
+
+    if has_old_packages {
+        let columns = if version == 2 {
+            VERSION_2_COLUMNS
+        } else {
+            VERSION_0_COLUMNS
+        };
+
+        transaction.execute_batch(RENAME_OLD_PACKAGES)?;
+        transaction.execute_batch(SCHEMA)?;
+        transaction.execute_batch(&format!(
+            "INSERT INTO packages ({columns}) SELECT {columns} FROM packages_old;
+             DROP TABLE packages_old;"
+        ))?;
+    } else {
+        transaction.execute_batch(SCHEMA)?;
+    }
+
+    transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    transaction.commit()
+}
+
+fn to_store_error(error: rusqlite::Error) -> StoreError {
+    StoreError::InternalStoreError(error.to_string())
+}
+
+fn upsert_category(connection: &Connection, category: &Category) -> StoreResult<()> {
+    connection
+        .execute(
+            "INSERT INTO categories (category_name, additional_info) VALUES (?1, ?2)
+             ON CONFLICT (category_name) DO UPDATE SET additional_info = excluded.additional_info",
+            params![category.category_name, category.additional_info],
+        )
+        .map_err(to_store_error)?;
+
+    Ok(())
+}
+
+fn upsert_package(connection: &Connection, package: &PackageData) -> StoreResult<()> {
+    connection
+        .execute(
+            "INSERT INTO packages (package_name, installation_tool, instance, category_name, installed, metadata)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+             ON CONFLICT (package_name, installation_tool, instance) DO UPDATE SET
+                category_name = excluded.category_name,
+                installed = excluded.installed,
+                metadata = excluded.metadata",
+            params![
+                package.package_name,
+                package.installation_tool.to_string(),
+                instance_column(&package.instance),
+                package.category_name,
+                package.installed,
+                metadata_to_json(&package.metadata)?
+            ],
+        )
+        .map_err(to_store_error)?;
+
+    Ok(())
+}
+
+fn category_from_row(row: &Row) -> rusqlite::Result<Category> {
+    Ok(Category {
+        category_name: row.get(0)?,
+        additional_info: row.get(1)?,
+    })
+}
+
+fn metadata_to_json(metadata: &PackageMetadata) -> StoreResult<String> {
+    serde_json::to_string(metadata).map_err(|e| StoreError::InternalStoreError(e.to_string()))
+}
+
+fn instance_column(instance: &Option<String>) -> &str {
+    instance.as_deref().unwrap_or_default()
+}
+
+fn package_from_row(row: &Row) -> rusqlite::Result<PackageData> {
+    let installation_tool: String = row.get(1)?;
+    let instance: String = row.get(2)?;
+    let metadata: String = row.get(5)?;
+
+    Ok(PackageData {
+        package_name: row.get(0)?,
+        installation_tool: installation_tool
+            .parse::<InstallationTools>()
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, e.into())
+            })?,
+        instance: Some(instance).filter(|instance| !instance.is_empty()),
+        category_name: row.get(3)?,
+        installed: row.get(4)?,
+        metadata: serde_json::from_str(&metadata).map_err(|e| {
+            rusqlite::Error::FromSqlConversionFailure(5, rusqlite::types::Type::Text, e.into())
+        })?,
+    })
+}
+
+const PACKAGE_COLUMNS: &str =
+    "package_name, installation_tool, instance, category_name, installed, metadata";
+
+impl PazckagerStorage for SqlitePazckagerStorage {
+    fn store_category(&mut self, category: Category) -> StoreResult<()> {
+        if self.category_exists(&category.category_name)? {
+            return Err(StoreError::AlreadyExists(format!(
+                "Category {}",
+                category.category_name
+            )));
+        }
+
+        upsert_category(&self.connection, &category)
+    }
+
+    fn get_categories(&self) -> StoreResult<Vec<Category>> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT category_name, additional_info FROM categories ORDER BY category_name")
+            .map_err(to_store_error)?;
+
+        statement
+            .query_map([], category_from_row)
+            .and_then(|rows| rows.collect())
+            .map_err(to_store_error)
+    }
+
+    fn get_category(&self, category_name: &str) -> StoreResult<Category> {
+        self.connection
+            .query_row(
+                "SELECT category_name, additional_info FROM categories WHERE category_name = ?1",
+                params![category_name],
+                category_from_row,
+            )
+            .optional()
+            .map_err(to_store_error)?
+            .ok_or(StoreError::NotFound(format!("Category {category_name}")))
+    }
+
+    fn category_exists(&self, category_name: &str) -> StoreResult<bool> {
+        self.connection
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM categories WHERE category_name = ?1)",
+                params![category_name],
+                |row| row.get(0),
+            )
+            .map_err(to_store_error)
+    }
+
+    fn remove_category(&mut self, category_name: &str) -> StoreResult<()> {
+        if !self.get_packages_by_category(category_name)?.is_empty() {
+            return Err(StoreError::InUse(format!("Category {category_name}")));
+        }
+
+        let deleted_rows = self
+            .connection
+            .execute(
+                "DELETE FROM categories WHERE category_name = ?1",
+                params![category_name],
+            )
+            .map_err(to_store_error)?;
+
+        if deleted_rows == 0 {
+            return Err(StoreError::NotFound(format!("Category {category_name}")));
+        }
+
+        Ok(())
+    }
+
+    fn store_package(&mut self, package: PackageData) -> StoreResult<()> {
+        if self.package_exists(&package.key())? {
+            return Err(StoreError::AlreadyExists(format!(
+                "Package {}",
+                package.key()
+            )));
+        }
+
+        self.ensure_category_exists(&package.category_name)?;
+        upsert_package(&self.connection, &package)
+    }
+
+    fn update_package(&mut self, package: PackageData) -> StoreResult<()> {
+        self.ensure_category_exists(&package.category_name)?;
+
+        let updated_rows = self
+            .connection
+            .execute(
+                "UPDATE packages SET category_name = ?4, installed = ?5, metadata = ?6
+                 WHERE package_name = ?1 AND installation_tool = ?2 AND instance = ?3",
+                params![
+                    package.package_name,
+                    package.installation_tool.to_string(),
+                    instance_column(&package.instance),
+                    package.category_name,
+                    package.installed,
+                    metadata_to_json(&package.metadata)?
+                ],
+            )
+            .map_err(to_store_error)?;
+
+        if updated_rows == 0 {
+            return Err(StoreError::NotFound(format!("Package {}", package.key())));
+        }
+
+        Ok(())
+    }
+
+    fn get_packages(&self) -> StoreResult<Vec<PackageData>> {
+        self.query_packages(
+            &format!(
+                "SELECT {PACKAGE_COLUMNS} FROM packages ORDER BY package_name, installation_tool, instance"
+            ),
+            [],
+        )
+    }
+
+    fn package_exists(&self, package_key: &PackageKey) -> StoreResult<bool> {
+        self.connection
+            .query_row(
+                "SELECT EXISTS (
+                    SELECT 1 FROM packages
+                    WHERE package_name = ?1 AND installation_tool = ?2 AND instance = ?3
+                )",
+                params![
+                    package_key.package_name,
+                    package_key.installation_tool.to_string(),
+                    instance_column(&package_key.instance)
+                ],
+                |row| row.get(0),
+            )
+            .map_err(to_store_error)
+    }
+
+    fn get_package(&self, package_key: &PackageKey) -> StoreResult<PackageData> {
+        self.connection
+            .query_row(
+                &format!(
+                    "SELECT {PACKAGE_COLUMNS} FROM packages
+                     WHERE package_name = ?1 AND installation_tool = ?2 AND instance = ?3"
+                ),
+                params![
+                    package_key.package_name,
+                    package_key.installation_tool.to_string(),
+                    instance_column(&package_key.instance)
+                ],
+                package_from_row,
+            )
+            .optional()
+            .map_err(to_store_error)?
+            .ok_or(StoreError::NotFound(format!("Package {package_key}")))
+    }
+
+    fn get_packages_by_name(&self, package_name: &str) -> StoreResult<Vec<PackageData>> {
+        self.query_packages(
+            &format!(
+                "SELECT {PACKAGE_COLUMNS} FROM packages WHERE package_name = ?1
+                 ORDER BY installation_tool, instance"
+            ),
+            params![package_name],
+        )
+    }
+
+    fn get_packages_by_category(&self, category_name: &str) -> StoreResult<Vec<PackageData>> {
+        self.query_packages(
+            &format!(
+                "SELECT {PACKAGE_COLUMNS} FROM packages WHERE category_name = ?1
+                 ORDER BY package_name, installation_tool, instance"
+            ),
+            params![category_name],
+        )
+    }
+
+    fn remove_package(&mut self, package_key: &PackageKey) -> StoreResult<()> {
+        let deleted_rows = self
+            .connection
+            .execute(
+                "DELETE FROM packages
+                 WHERE package_name = ?1 AND installation_tool = ?2 AND instance = ?3",
+                params![
+                    package_key.package_name,
+                    package_key.installation_tool.to_string(),
+                    instance_column(&package_key.instance)
+                ],
+            )
+            .map_err(to_store_error)?;
+
+        if deleted_rows == 0 {
+            return Err(StoreError::NotFound(format!("Package {package_key}")));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_json_storage::JsonPazckagerStorage;
+
+    pazckager_core::storage_conformance_tests!(storage => {
+        let mut storage = SqlitePazckagerStorage::in_memory().unwrap();
+    });
+
+    fn test_category(category_name: &str) -> Category {
+        Category {
+            category_name: category_name.to_string(),
+            additional_info: Some("info".to_string()),
+        }
+    }
+
+    fn test_package(package_name: &str, category_name: &str) -> PackageData {
+        PackageData {
+            package_name: package_name.to_string(),
+            installation_tool: InstallationTools::Pacman,
+            instance: None,
+            category_name: category_name.to_string(),
+            installed: true,
+            metadata: PackageMetadata::new(),
+        }
+    }
+
+    #[test]
+    fn test_sqlite_storage() -> StoreResult<()> {
+        let mut storage = SqlitePazckagerStorage::in_memory()?;
+
+        let category = test_category("test");
+        storage.store_category(category.clone())?;
+        assert!(storage.category_exists("test")?);
+        assert_eq!(storage.get_categories()?, vec![category]);
+
+        let package = test_package("test_pkg", "test");
+        storage.store_package(package.clone())?;
+        assert!(storage.package_exists(&package.key())?);
+        assert_eq!(storage.get_packages()?, vec![package.clone()]);
+        assert_eq!(
+            storage.get_packages_by_category("test")?,
+            vec![package.clone()]
+        );
+
+        storage.remove_package(&package.key())?;
+        assert!(!storage.package_exists(&package.key())?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_requires_existing_category() -> StoreResult<()> {
+        let mut storage = SqlitePazckagerStorage::in_memory()?;
+
+        assert!(
+            storage
+                .store_package(test_package("test_pkg", "missing"))
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_from() -> StoreResult<()> {
+        let mut source = SqlitePazckagerStorage::in_memory()?;
+        source.store_category(test_category("test"))?;
+        source.store_package(test_package("test_pkg", "test"))?;
+
+        let mut storage = SqlitePazckagerStorage::in_memory()?;
+        storage.import_from(&source)?;
+
+        assert_eq!(storage.get_categories()?, source.get_categories()?);
+        assert_eq!(storage.get_packages()?, source.get_packages()?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_import_json_store() -> StoreResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let json_path = temp_dir.path().join("pazckager_store.json");
+        std::fs::write(
+            &json_path,
+            r#"{
+                "categories": {
+                    "default": { "category_name": "default", "additional_info": null },
+                    "test": { "category_name": "test", "additional_info": "info" }
+                },
+                "packages": {
+                    "test_pkg": {
+                        "package_name": "test_pkg",
+                        "installation_tool": "Pacman",
+                        "category_name": "test",
+                        "installed": true
+                    }
+                }
+            }"#,
+        )
+        .unwrap();
+
+        let source = JsonPazckagerStorage::open(json_path.to_str().unwrap())?;
+        let mut storage = SqlitePazckagerStorage::in_memory()?;
+        storage.import_from(&source)?;
+
+        assert!(storage.category_exists("default")?);
+        assert_eq!(
+            storage.get_category("test")?.additional_info,
+            Some("info".to_string())
+        );
+        assert_eq!(
+            storage.get_packages()?,
+            vec![test_package("test_pkg", "test")]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_from_version_0() -> StoreResult<()> {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE categories (
+                    category_name TEXT PRIMARY KEY NOT NULL,
+                    additional_info TEXT
+                );
+                CREATE TABLE packages (
+                    package_name TEXT PRIMARY KEY NOT NULL,
+                    installation_tool TEXT NOT NULL,
+                    category_name TEXT NOT NULL REFERENCES categories (category_name),
+                    installed INTEGER NOT NULL
+                );
+                CREATE INDEX packages_category_name_idx ON packages (category_name);
+                CREATE INDEX packages_installation_tool_idx ON packages (installation_tool);
+                INSERT INTO categories VALUES ('test', 'info');
+                INSERT INTO packages VALUES ('test_pkg', 'pacman', 'test', 1);",
+            )
+            .unwrap();
+
+        let mut storage = SqlitePazckagerStorage::from_connection(connection)?;
+        assert_eq!(
+            storage.get_packages()?,
+            vec![test_package("test_pkg", "test")]
+        );
+
+        storage.store_package(PackageData {
+            installation_tool: InstallationTools::Yay,
+            ..test_package("test_pkg", "test")
+        })?;
+        assert_eq!(storage.get_packages_by_name("test_pkg")?.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_from_version_1() -> StoreResult<()> {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE categories (
+                    category_name TEXT PRIMARY KEY NOT NULL,
+                    additional_info TEXT
+                );
+                CREATE TABLE packages (
+                    package_name TEXT NOT NULL,
+                    installation_tool TEXT NOT NULL,
+                    category_name TEXT NOT NULL REFERENCES categories (category_name),
+                    installed INTEGER NOT NULL,
+                    PRIMARY KEY (package_name, installation_tool)
+                );
+                INSERT INTO categories VALUES ('test', 'info');
+                INSERT INTO packages VALUES ('test_pkg', 'pacman', 'test', 1);
+                PRAGMA user_version = 1;",
+            )
+            .unwrap();
+
+        let mut storage = SqlitePazckagerStorage::from_connection(connection)?;
+        assert_eq!(
+            storage.get_packages()?,
+            vec![test_package("test_pkg", "test")]
+        );
+
+        let package = PackageData {
+            metadata: PackageMetadata::from([("remote".to_string(), "flathub".to_string())]),
+            ..test_package("test_pkg", "test")
+        };
+        storage.update_package(package.clone())?;
+        assert_eq!(storage.get_package(&package.key())?, package);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_migrate_from_version_2() -> StoreResult<()> {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .execute_batch(
+                "CREATE TABLE categories (
+                    category_name TEXT PRIMARY KEY NOT NULL,
+                    additional_info TEXT
+                );
+                CREATE TABLE packages (
+                    package_name TEXT NOT NULL,
+                    installation_tool TEXT NOT NULL,
+                    category_name TEXT NOT NULL REFERENCES categories (category_name),
+                    installed INTEGER NOT NULL,
+                    metadata TEXT NOT NULL DEFAULT '{}',
+                    PRIMARY KEY (package_name, installation_tool)
+                );
+                INSERT INTO categories VALUES ('test', 'info');
+                INSERT INTO packages VALUES ('test_pkg', 'pacman', 'test', 1, '{\"repo\":\"extra\"}');
+                PRAGMA user_version = 2;",
+            )
+            .unwrap();
+
+        let mut storage = SqlitePazckagerStorage::from_connection(connection)?;
+        let package = PackageData {
+            metadata: PackageMetadata::from([("repo".to_string(), "extra".to_string())]),
+            ..test_package("test_pkg", "test")
+        };
+        assert_eq!(storage.get_packages()?, vec![package.clone()]);
+
+        let chroot_package = PackageData {
+            instance: Some("chroot".to_string()),
+            ..package
+        };
+        storage.store_package(chroot_package.clone())?;
+        assert_eq!(storage.get_package(&chroot_package.key())?, chroot_package);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,670 @@
+use pazckager_core::models::{
+    Category, InstallationTools, InstallerId, PackageData, PackageKey, PackageMetadata,
+};
+use pazckager_core::traits::{PazckagerStorage, StoreError, StoreResult};
+use std::fs;
+use std::path::PathBuf;
+use toml_edit::{DocumentMut, InlineTable, Item, Key, Table, value};
+
+const ADDITIONAL_INFO_KEY: &str = "additional_info";
+const PACKAGES_KEY: &str = "packages";
+const INSTALLATION_TOOL_KEY: &str = "installation_tool";
+const INSTALLED_KEY: &str = "installed";
+const METADATA_KEY: &str = "metadata";
+
+// The manifest has one table per category, with its packages grouped by
+// installer as inline tables, named instances get their own table:
+//
+// [dev]
+// additional_info = "Development tools"
+//
+// [dev.packages.pacman]
+// ripgrep = { installed = true }
+//
+// [dev.packages.flatpak]
+// "org.gnome.Builder" = { installed = true, metadata = { remote = "flathub" } }
+//
+// [dev.packages."pacman@chroot"]
+// base-devel = { installed = true }
+pub struct TomlPazckagerStorage {
+    document: DocumentMut,
+    file_path: PathBuf,
+}
+
+impl TomlPazckagerStorage {
+    pub fn new(file_path: &str) -> StoreResult<Self> {
+        let expanded_path = shellexpand::tilde(&file_path).to_string();
+        let file_path = PathBuf::from(expanded_path);
+
+        if let Some(parent) = file_path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| StoreError::InternalStoreError(e.to_string()))?;
+        }
+
+        let mut document = if file_path.exists() {
+            fs::read_to_string(&file_path)
+                .map_err(|e| StoreError::InternalStoreError(e.to_string()))?
+                .parse::<DocumentMut>()
+                .map_err(|e| StoreError::InternalStoreError(e.to_string()))?
+        } else {
+            DocumentMut::new()
+        };
+
+        migrate_flat_packages(&mut document)?;
+
+        Ok(Self {
+            document,
+            file_path,
+        })
+    }
+
+    fn save_to_file(&mut self) -> StoreResult<()> {
+        self.sort_document();
+
+        fs::write(&self.file_path, self.document.to_string())
+            .map_err(|e| StoreError::InternalStoreError(e.to_string()))?;
+
+        Ok(())
+    }
+
+    // Keeps categories and packages in alphabetical order so that the file
+    // produces stable diffs, whatever order the entries were added in.
+    fn sort_document(&mut self) {
+        self.document.sort_values();
+
+        let mut position = 0;
+        for (_, item) in self.document.iter_mut() {
+            let Some(category_table) = item.as_table_mut() else {
+                continue;
+            };
+
+            category_table.set_position(Some(position));
+            position += 1;
+
+            let Some(packages_table) = category_table
+                .get_mut(PACKAGES_KEY)
+                .and_then(Item::as_table_mut)
+            else {
+                continue;
+            };
+
+            packages_table.sort_values();
+            packages_table.set_position(Some(position));
+            position += 1;
+
+            for (_, tool_item) in packages_table.iter_mut() {
+                if let Some(tool_table) = tool_item.as_table_mut() {
+                    tool_table.sort_values();
+                    tool_table.set_position(Some(position));
+                    position += 1;
+                }
+            }
+        }
+    }
+
+    fn categories(&self) -> impl Iterator<Item = (&str, &Table)> {
+        self.document
+            .iter()
+            .filter_map(|(category_name, item)| Some((category_name, item.as_table()?)))
+    }
+
+    fn find_package_category(&self, package_key: &PackageKey) -> StoreResult<Option<String>> {
+        for (category_name, category_table) in self.categories() {
+            for (installer, tool_table) in tool_tables(category_table)? {
+                if installer == package_key.installer()
+                    && tool_table.contains_key(&package_key.package_name)
+                {
+                    return Ok(Some(category_name.to_string()));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    fn ensure_category_exists(&self, category_name: &str) -> StoreResult<()> {
+        if !self.category_exists(category_name)? {
+            return Err(StoreError::NotFound(format!("Category {category_name}")));
+        }
+
+        Ok(())
+    }
+
+    fn category_table_mut(&mut self, category_name: &str) -> StoreResult<&mut Table> {
+        let item = self
+            .document
+            .entry(category_name)
+            .or_insert_with(|| Item::Table(Table::new()));
+
+        item.as_table_mut().ok_or_else(|| {
+            StoreError::InternalStoreError(format!("Category {category_name} must be a table"))
+        })
+    }
+
+    fn tool_table_mut(
+        &mut self,
+        category_name: &str,
+        installer: &InstallerId,
+    ) -> StoreResult<&mut Table> {
+        let category_table = self.category_table_mut(category_name)?;
+        tool_table_mut(category_table, installer)
+    }
+
+    fn remove_package_entry(
+        &mut self,
+        package_key: &PackageKey,
+    ) -> StoreResult<Option<(Key, Item)>> {
+        let Some(category_name) = self.find_package_category(package_key)? else {
+            return Ok(None);
+        };
+
+        let category_table = self.category_table_mut(&category_name)?;
+        let tool_table = tool_table_mut(category_table, &package_key.installer())?;
+        let entry = tool_table.remove_entry(&package_key.package_name);
+
+        if tool_table.is_empty()
+            && let Some(packages_table) = category_table
+                .get_mut(PACKAGES_KEY)
+                .and_then(Item::as_table_mut)
+        {
+            packages_table.remove(&package_key.installer().to_string());
+        }
+
+        Ok(entry)
+    }
+}
+
+fn tool_tables(category_table: &Table) -> StoreResult<Vec<(InstallerId, &Table)>> {
+    let Some(packages_item) = category_table.get(PACKAGES_KEY) else {
+        return Ok(Vec::new());
+    };
+
+    let packages_table = packages_item.as_table().ok_or_else(|| {
+        StoreError::InternalStoreError("Packages entry must be a table".to_string())
+    })?;
+
+    packages_table
+        .iter()
+        .map(|(tool_name, item)| {
+            let installer = tool_name.parse::<InstallerId>().map_err(|e| {
+                StoreError::InternalStoreError(format!("Packages table {tool_name}: {e}"))
+            })?;
+            let tool_table = item.as_table().ok_or_else(|| {
+                StoreError::InternalStoreError(format!(
+                    "Packages table {tool_name} must be a table"
+                ))
+            })?;
+
+            Ok((installer, tool_table))
+        })
+        .collect()
+}
+
+fn tool_table_mut<'a>(
+    category_table: &'a mut Table,
+    installer: &InstallerId,
+) -> StoreResult<&'a mut Table> {
+    let packages_item = category_table.entry(PACKAGES_KEY).or_insert_with(|| {
+        let mut packages_table = Table::new();
+        packages_table.set_implicit(true);
+        Item::Table(packages_table)
+    });
+
+    let packages_table = packages_item.as_table_mut().ok_or_else(|| {
+        StoreError::InternalStoreError("Packages entry must be a table".to_string())
+    })?;
+
+    packages_table
+        .entry(&installer.to_string())
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| {
+            StoreError::InternalStoreError(format!("Packages table {installer} must be a table"))
+        })
+}
+
+// Manifests written before packages were identified by tool plus name keep
+// every package directly under `packages`, with an installation_tool field:
+//
+// [dev.packages]
+// ripgrep = { installation_tool = "pacman", installed = true }
+//
+// Those entries are moved under their tool table, keeping their comments.
+fn migrate_flat_packages(document: &mut DocumentMut) -> StoreResult<()> {
+    for (_, item) in document.iter_mut() {
+        let Some(packages_table) = item
+            .as_table_mut()
+            .and_then(|category_table| category_table.get_mut(PACKAGES_KEY))
+            .and_then(Item::as_table_mut)
+        else {
+            continue;
+        };
+
+        let flat_package_names: Vec<String> = packages_table
+            .iter()
+            .filter(|(_, item)| {
+                item.as_table_like()
+                    .is_some_and(|entry| entry.contains_key(INSTALLATION_TOOL_KEY))
+            })
+            .map(|(package_name, _)| package_name.to_string())
+            .collect();
+
+        if !flat_package_names.is_empty() {
+            packages_table.set_implicit(true);
+        }
+
+        for package_name in flat_package_names {
+            let Some((key, item)) = packages_table.remove_entry(&package_name) else {
+                continue;
+            };
+
+            let installation_tool = item
+                .get(INSTALLATION_TOOL_KEY)
+                .and_then(Item::as_str)
+                .ok_or_else(|| {
+                    StoreError::InternalStoreError(format!(
+                        "Package {package_name}: invalid installation_tool"
+                    ))
+                })?
+                .parse::<InstallationTools>()
+                .map_err(|e| {
+                    StoreError::InternalStoreError(format!("Package {package_name}: {e}"))
+                })?;
+
+            let installed = item
+                .get(INSTALLED_KEY)
+                .and_then(Item::as_bool)
+                .unwrap_or(false);
+
+            packages_table
+                .entry(&installation_tool.to_string())
+                .or_insert_with(|| Item::Table(Table::new()))
+                .as_table_mut()
+                .ok_or_else(|| {
+                    StoreError::InternalStoreError(format!(
+                        "Packages table {installation_tool} must be a table"
+                    ))
+                })?
+                .insert_formatted(&key, package_entry(installed, &PackageMetadata::new()));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_package(
+    category_name: &str,
+    installer: &InstallerId,
+    package_name: &str,
+    item: &Item,
+) -> StoreResult<PackageData> {
+    let package_table = item.as_table_like().ok_or_else(|| {
+        StoreError::InternalStoreError(format!("Package {package_name}: entry must be a table"))
+    })?;
+
+    let installed = package_table
+        .get(INSTALLED_KEY)
+        .and_then(Item::as_bool)
+        .unwrap_or(false);
+
+    let mut metadata = PackageMetadata::new();
+
+    if let Some(metadata_item) = package_table.get(METADATA_KEY) {
+        let metadata_table = metadata_item.as_table_like().ok_or_else(|| {
+            StoreError::InternalStoreError(format!(
+                "Package {package_name}: metadata must be a table"
+            ))
+        })?;
+
+        for (key, item) in metadata_table.iter() {
+            let value = item.as_str().ok_or_else(|| {
+                StoreError::InternalStoreError(format!(
+                    "Package {package_name}: metadata {key} must be a string"
+                ))
+            })?;
+            metadata.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    Ok(PackageData {
+        package_name: package_name.to_string(),
+        installation_tool: installer.installation_tool.clone(),
+        instance: installer.instance.clone(),
+        category_name: category_name.to_string(),
+        installed,
+        metadata,
+    })
+}
+
+fn package_entry(installed: bool, metadata: &PackageMetadata) -> Item {
+    let mut package_table = InlineTable::new();
+    package_table.insert(INSTALLED_KEY, installed.into());
+
+    if !metadata.is_empty() {
+        let mut metadata_table = InlineTable::new();
+        for (key, value) in metadata {
+            metadata_table.insert(key, value.into());
+        }
+        package_table.insert(METADATA_KEY, metadata_table.into());
+    }
+
+    package_table.fmt();
+
+    value(package_table)
+}
+
+impl PazckagerStorage for TomlPazckagerStorage {
+    fn store_category(&mut self, category: Category) -> StoreResult<()> {
+        if self.category_exists(&category.category_name)? {
+            return Err(StoreError::AlreadyExists(format!(
+                "Category {}",
+                category.category_name
+            )));
+        }
+
+        let category_table = self.category_table_mut(&category.category_name)?;
+
+        match category.additional_info {
+            Some(additional_info) => {
+                category_table.insert(ADDITIONAL_INFO_KEY, value(additional_info));
+            }
+            None => {
+                category_table.remove(ADDITIONAL_INFO_KEY);
+            }
+        }
+
+        self.save_to_file()
+    }
+
+    fn get_categories(&self) -> StoreResult<Vec<Category>> {
+        Ok(self
+            .categories()
+            .map(|(category_name, category_table)| Category {
+                category_name: category_name.to_string(),
+                additional_info: category_table
+                    .get(ADDITIONAL_INFO_KEY)
+                    .and_then(Item::as_str)
+                    .map(str::to_string),
+            })
+            .collect())
+    }
+
+    fn get_category(&self, category_name: &str) -> StoreResult<Category> {
+        self.get_categories()?
+            .into_iter()
+            .find(|category| category.category_name == category_name)
+            .ok_or(StoreError::NotFound(format!("Category {category_name}")))
+    }
+
+    fn category_exists(&self, category_name: &str) -> StoreResult<bool> {
+        Ok(self
+            .categories()
+            .any(|(existing_name, _)| existing_name == category_name))
+    }
+
+    fn remove_category(&mut self, category_name: &str) -> StoreResult<()> {
+        self.ensure_category_exists(category_name)?;
+        if !self.get_packages_by_category(category_name)?.is_empty() {
+            return Err(StoreError::InUse(format!("Category {category_name}")));
+        }
+
+        self.document.remove(category_name);
+        self.save_to_file()
+    }
+
+    fn store_package(&mut self, package: PackageData) -> StoreResult<()> {
+        if self.package_exists(&package.key())? {
+            return Err(StoreError::AlreadyExists(format!(
+                "Package {}",
+                package.key()
+            )));
+        }
+
+        self.ensure_category_exists(&package.category_name)?;
+        self.tool_table_mut(&package.category_name, &package.installer())?
+            .insert(
+                &package.package_name,
+                package_entry(package.installed, &package.metadata),
+            );
+
+        self.save_to_file()
+    }
+
+    fn update_package(&mut self, package: PackageData) -> StoreResult<()> {
+        self.ensure_category_exists(&package.category_name)?;
+
+        // The previous key is reused so that comments above the entry follow it
+        // when the package moves to another category.
+        let (key, _) = self
+            .remove_package_entry(&package.key())?
+            .ok_or(StoreError::NotFound(format!("Package {}", package.key())))?;
+
+        self.tool_table_mut(&package.category_name, &package.installer())?
+            .insert_formatted(&key, package_entry(package.installed, &package.metadata));
+
+        self.save_to_file()
+    }
+
+    fn get_packages(&self) -> StoreResult<Vec<PackageData>> {
+        let mut packages = Vec::new();
+
+        for (category_name, _) in self.categories() {
+            packages.extend(self.get_packages_by_category(category_name)?);
+        }
+
+        Ok(packages)
+    }
+
+    fn package_exists(&self, package_key: &PackageKey) -> StoreResult<bool> {
+        Ok(self.find_package_category(package_key)?.is_some())
+    }
+
+    fn get_package(&self, package_key: &PackageKey) -> StoreResult<PackageData> {
+        let not_found = || StoreError::NotFound(format!("Package {package_key}"));
+
+        let category_name = self
+            .find_package_category(package_key)?
+            .ok_or_else(not_found)?;
+
+        self.get_packages_by_category(&category_name)?
+            .into_iter()
+            .find(|package| package.key() == *package_key)
+            .ok_or_else(not_found)
+    }
+
+    fn get_packages_by_name(&self, package_name: &str) -> StoreResult<Vec<PackageData>> {
+        Ok(self
+            .get_packages()?
+            .into_iter()
+            .filter(|package| package.package_name == package_name)
+            .collect())
+    }
+
+    fn get_packages_by_category(&self, category_name: &str) -> StoreResult<Vec<PackageData>> {
+        let Some(category_table) = self.document.get(category_name).and_then(Item::as_table) else {
+            return Ok(Vec::new());
+        };
+
+        let mut packages = Vec::new();
+
+        for (installer, tool_table) in tool_tables(category_table)? {
+            for (package_name, item) in tool_table.iter() {
+                packages.push(parse_package(
+                    category_name,
+                    &installer,
+                    package_name,
+                    item,
+                )?);
+            }
+        }
+
+        Ok(packages)
+    }
+
+    fn remove_package(&mut self, package_key: &PackageKey) -> StoreResult<()> {
+        self.remove_package_entry(package_key)?
+            .ok_or(StoreError::NotFound(format!("Package {package_key}")))?;
+
+        self.save_to_file()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_package(package_name: &str, category_name: &str) -> PackageData {
+        PackageData {
+            package_name: package_name.to_string(),
+            installation_tool: InstallationTools::Pacman,
+            instance: None,
+            category_name: category_name.to_string(),
+            installed: true,
+            metadata: PackageMetadata::new(),
+        }
+    }
+
+    pazckager_core::storage_conformance_tests!(storage => {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().join("store.toml");
+        let mut storage = TomlPazckagerStorage::new(store_path.to_str().unwrap()).unwrap();
+    });
+
+    #[test]
+    fn test_toml_storage() -> StoreResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().join("store.toml");
+        let mut storage = TomlPazckagerStorage::new(store_path.to_str().unwrap())?;
+
+        let category = Category {
+            category_name: "test".to_string(),
+            additional_info: Some("info".to_string()),
+        };
+        storage.store_category(category.clone())?;
+        assert!(storage.category_exists("test")?);
+        assert_eq!(storage.get_categories()?, vec![category]);
+
+        let package = test_package("test_pkg", "test");
+        storage.store_package(package.clone())?;
+        assert!(storage.package_exists(&package.key())?);
+        assert_eq!(storage.get_packages()?, vec![package.clone()]);
+
+        let reopened_storage = TomlPazckagerStorage::new(store_path.to_str().unwrap())?;
+        assert_eq!(reopened_storage.get_package(&package.key())?, package);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_is_sorted_and_keeps_comments() -> StoreResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().join("store.toml");
+        fs::write(
+            &store_path,
+            "# Tools for work\n\
+             [work]\n\
+             \n\
+             [work.packages.pacman]\n\
+             # Needed by the build scripts\n\
+             zsh = { installed = true }\n\
+             [base]\n",
+        )
+        .unwrap();
+
+        let mut storage = TomlPazckagerStorage::new(store_path.to_str().unwrap())?;
+        storage.store_package(test_package("git", "work"))?;
+        storage.update_package(PackageData {
+            installed: false,
+            ..test_package("zsh", "work")
+        })?;
+
+        assert_eq!(
+            fs::read_to_string(&store_path).unwrap(),
+            "[base]\n\
+             # Tools for work\n\
+             [work]\n\
+             \n\
+             [work.packages.pacman]\n\
+             git = { installed = true }\n\
+             # Needed by the build scripts\n\
+             zsh = { installed = false }\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flat_packages_are_migrated() -> StoreResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().join("store.toml");
+        fs::write(
+            &store_path,
+            "[work]\n\
+             \n\
+             [work.packages]\n\
+             # Needed by the build scripts\n\
+             zsh = { installation_tool = \"pacman\", installed = true }\n",
+        )
+        .unwrap();
+
+        let mut storage = TomlPazckagerStorage::new(store_path.to_str().unwrap())?;
+        let package = test_package("zsh", "work");
+        assert_eq!(storage.get_package(&package.key())?, package);
+
+        storage.store_package(PackageData {
+            installation_tool: InstallationTools::Yay,
+            ..test_package("zsh", "work")
+        })?;
+
+        assert_eq!(
+            fs::read_to_string(&store_path).unwrap(),
+            "[work]\n\
+             \n\
+             [work.packages.pacman]\n\
+             # Needed by the build scripts\n\
+             zsh = { installed = true }\n\
+             \n\
+             [work.packages.yay]\n\
+             zsh = { installed = true }\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_instances_get_their_own_table() -> StoreResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().join("store.toml");
+        let mut storage = TomlPazckagerStorage::new(store_path.to_str().unwrap())?;
+        storage.store_category(Category {
+            category_name: "base".to_string(),
+            additional_info: None,
+        })?;
+
+        let chroot_package = PackageData {
+            instance: Some("chroot".to_string()),
+            ..test_package("base-devel", "base")
+        };
+        storage.store_package(test_package("base-devel", "base"))?;
+        storage.store_package(chroot_package.clone())?;
+
+        assert_eq!(
+            fs::read_to_string(&store_path).unwrap(),
+            "[base]\n\
+             \n\
+             [base.packages.pacman]\n\
+             base-devel = { installed = true }\n\
+             \n\
+             [base.packages.\"pacman@chroot\"]\n\
+             base-devel = { installed = true }\n"
+        );
+
+        let reopened_storage = TomlPazckagerStorage::new(store_path.to_str().unwrap())?;
+        assert_eq!(
+            reopened_storage.get_package(&chroot_package.key())?,
+            chroot_package
+        );
+
+        Ok(())
+    }
+}
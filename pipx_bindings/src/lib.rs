@@ -0,0 +1,269 @@
+use pazckager_core::models::{InstallationTools, PackageData, PackageMetadata, RawPackageData};
+use pazckager_core::program::{RunControl, find_program, listing_output, program_version};
+use pazckager_core::traits::{InstallationTool, ToolCapability, ToolError, ToolResult};
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::process::Command;
+
+// Package metadata key with the exact version pipx installs. Packages synced
+// from pipx list get it when they were installed with "==version"
+pub const VERSION_KEY: &str = "version";
+
+#[derive(Deserialize)]
+struct PipxList {
+    venvs: BTreeMap<String, PipxVenv>,
+}
+
+#[derive(Deserialize)]
+struct PipxVenv {
+    metadata: PipxVenvMetadata,
+}
+
+#[derive(Deserialize)]
+struct PipxVenvMetadata {
+    main_package: PipxPackage,
+}
+
+#[derive(Deserialize)]
+struct PipxPackage {
+    package: String,
+    package_or_url: String,
+}
+
+pub struct PipxInstaller {
+    program: PathBuf,
+    home: Option<PathBuf>,
+    bin_dir: Option<PathBuf>,
+    control: RunControl,
+}
+
+impl PipxInstaller {
+    pub fn new() -> Self {
+        Self {
+            program: PathBuf::from("pipx"),
+            home: None,
+            bin_dir: None,
+            control: RunControl::default(),
+        }
+    }
+
+    pub fn with_program(mut self, program: impl Into<PathBuf>) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    // Per user prefix, passed to pipx as PIPX_HOME and PIPX_BIN_DIR
+    pub fn with_home(mut self, home: impl Into<PathBuf>) -> Self {
+        self.home = Some(home.into());
+        self
+    }
+
+    pub fn with_bin_dir(mut self, bin_dir: impl Into<PathBuf>) -> Self {
+        self.bin_dir = Some(bin_dir.into());
+        self
+    }
+
+    fn create_command(&self, subcommand: &str) -> Command {
+        let mut command = Command::new(&self.program);
+        command.arg(subcommand);
+
+        if let Some(home) = &self.home {
+            command.env("PIPX_HOME", home);
+        }
+
+        if let Some(bin_dir) = &self.bin_dir {
+            command.env("PIPX_BIN_DIR", bin_dir);
+        }
+
+        command
+    }
+}
+
+impl Default for PipxInstaller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn package_spec(package: &PackageData) -> String {
+    package.versioned_name(VERSION_KEY, "==")
+}
+
+fn parse_pipx_list(output: &str) -> ToolResult<Vec<RawPackageData>> {
+    let pipx_list = serde_json::from_str::<PipxList>(output)
+        .map_err(|e| ToolError::ListingPackages(format!("Invalid pipx list output: {e}")))?;
+
+    Ok(pipx_list
+        .venvs
+        .into_values()
+        .map(|venv| {
+            let main_package = venv.metadata.main_package;
+            let metadata = main_package
+                .package_or_url
+                .split_once("==")
+                .map(|(_, version)| (VERSION_KEY.to_string(), version.to_string()))
+                .into_iter()
+                .collect::<PackageMetadata>();
+
+            RawPackageData {
+                package_name: main_package.package,
+                category_name: String::new(),
+                installation_tool: InstallationTools::Pipx,
+                metadata,
+            }
+        })
+        .collect())
+}
+
+impl InstallationTool for PipxInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Pipx
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn is_available(&self) -> bool {
+        find_program(&self.program).is_some()
+    }
+
+    fn version(&self) -> Option<String> {
+        program_version(&self.program)
+    }
+
+    fn capabilities(&self) -> BTreeSet<ToolCapability> {
+        BTreeSet::from([ToolCapability::Versions, ToolCapability::UserScope])
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = self.create_command("install");
+        command.arg(package_spec(package));
+
+        self.control.run(
+            &mut command,
+            "pipx",
+            &package.package_name,
+            "install",
+            ToolError::InstallingPackage,
+        )
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = self.create_command("uninstall");
+        command.arg(&package.package_name);
+
+        self.control.run(
+            &mut command,
+            "pipx",
+            &package.package_name,
+            "remove",
+            ToolError::DeletingPackage,
+        )
+    }
+
+    // pipx upgrade ignores the original version specifier, so pinned packages
+    // are reinstalled at their version instead
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        let mut command = if package.metadata.contains_key(VERSION_KEY) {
+            let mut command = self.create_command("install");
+            command.args(["--force", &package_spec(package)]);
+            command
+        } else {
+            let mut command = self.create_command("upgrade");
+            command.arg(&package.package_name);
+            command
+        };
+
+        self.control.run(
+            &mut command,
+            "pipx",
+            &package.package_name,
+            "update",
+            ToolError::UpdatingPackage,
+        )
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        let mut command = self.create_command("list");
+        command.arg("--json");
+
+        parse_pipx_list(&listing_output(command, &self.control)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::{calls, fake_program, test_package};
+
+    const PIPX_LIST: &str = include_str!("../fixtures/pipx_list.json");
+
+    // Records its arguments and PIPX_HOME next to itself
+    const FAKE_PIPX: &str = "#!/bin/sh
+echo \"$PIPX_HOME $@\" >> \"$0.calls\"
+";
+
+    #[test]
+    fn test_commands() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let program = fake_program(temp_dir.path(), "pipx", FAKE_PIPX);
+        let mut installer = PipxInstaller::new()
+            .with_program(&program)
+            .with_home("/opt/pipx");
+
+        installer.install_package(&test_package(
+            InstallationTools::Pipx,
+            "black",
+            &[(VERSION_KEY, "24.4.2")],
+        ))?;
+        installer.update_package(&test_package(
+            InstallationTools::Pipx,
+            "black",
+            &[(VERSION_KEY, "24.4.2")],
+        ))?;
+        installer.update_package(&test_package(InstallationTools::Pipx, "httpie", &[]))?;
+        installer.delete_package(&test_package(InstallationTools::Pipx, "httpie", &[]))?;
+
+        assert_eq!(
+            calls(&program),
+            vec![
+                "/opt/pipx install black==24.4.2",
+                "/opt/pipx install --force black==24.4.2",
+                "/opt/pipx upgrade httpie",
+                "/opt/pipx uninstall httpie",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_pipx_list() {
+        let packages = parse_pipx_list(PIPX_LIST).unwrap();
+
+        assert_eq!(
+            packages
+                .into_iter()
+                .map(|package| (package.package_name, package.metadata))
+                .collect::<Vec<_>>(),
+            vec![
+                (
+                    "black".to_string(),
+                    test_package(InstallationTools::Pipx, "black", &[(VERSION_KEY, "24.4.2")])
+                        .metadata
+                ),
+                ("httpie".to_string(), PackageMetadata::new()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_pipx_list() {
+        assert!(matches!(
+            parse_pipx_list("pipx is not installed"),
+            Err(ToolError::ListingPackages(_))
+        ));
+    }
+}
@@ -0,0 +1,476 @@
+use pazckager_core::models::{InstallationTools, PackageData, PackageMetadata, RawPackageData};
+use pazckager_core::program::{RunControl, listing_output};
+use pazckager_core::traits::{InstallationTool, ToolError, ToolResult};
+use regex::Regex;
+use serde::Deserialize;
+use std::process::Command;
+use thiserror::Error;
+
+const PACKAGE_PLACEHOLDER: &str = "package";
+
+// Installer for tools without their own crate, read from the config file:
+//
+// [[script_installers]]
+// name = "go"
+// install = "go install {package}@{version}"
+// remove = "rm ~/go/bin/{package}"
+// update = "go install {package}@latest"
+// list = "ls ~/go/bin"
+// list_regex = '^(?P<package>\S+)$'
+//
+// Commands run through `sh -c`. `{package}` is replaced by the package name
+// and any other `{key}` by the package metadata with that key, both shell
+// quoted, missing keys are replaced by an empty string.
+//
+// The list output is parsed either with `list_regex`, matched against every
+// line, whose `package` group is the package name and other named groups are
+// stored as metadata, or with `list_json_path`, selecting the package names:
+// `$.dependencies.*~` takes the keys of the dependencies object and
+// `$.items.*.name` the name of every item.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ScriptConfig {
+    pub name: String,
+    pub install: String,
+    pub remove: String,
+    pub update: String,
+    pub list: String,
+    pub list_regex: Option<String>,
+    pub list_json_path: Option<String>,
+}
+
+#[derive(Debug, Error)]
+pub enum ScriptConfigError {
+    #[error("Script installer {0} needs either list_regex or list_json_path")]
+    MissingListParser(String),
+    #[error("Script installer {0} can't have both list_regex and list_json_path")]
+    SeveralListParsers(String),
+    #[error("Script installer {0} has an invalid list_regex: {1}")]
+    InvalidListRegex(String, String),
+    #[error("Script installer {0} list_regex needs a `package` named group")]
+    MissingPackageGroup(String),
+    #[error("Script installer {0} has an invalid list_json_path: {1}")]
+    InvalidJsonPath(String, String),
+}
+
+enum ListParser {
+    Regex(Regex),
+    JsonPath(Vec<JsonPathSegment>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum JsonPathSegment {
+    Key(String),
+    Values,
+    Keys,
+}
+
+pub struct ScriptInstaller {
+    config: ScriptConfig,
+    list_parser: ListParser,
+    placeholder_regex: Regex,
+    control: RunControl,
+}
+
+impl ScriptInstaller {
+    pub fn new(config: ScriptConfig) -> Result<Self, ScriptConfigError> {
+        let list_parser = match (&config.list_regex, &config.list_json_path) {
+            (Some(list_regex), None) => {
+                let regex = Regex::new(list_regex).map_err(|e| {
+                    ScriptConfigError::InvalidListRegex(config.name.clone(), e.to_string())
+                })?;
+
+                if !regex
+                    .capture_names()
+                    .any(|name| name == Some(PACKAGE_PLACEHOLDER))
+                {
+                    return Err(ScriptConfigError::MissingPackageGroup(config.name.clone()));
+                }
+
+                ListParser::Regex(regex)
+            }
+            (None, Some(list_json_path)) => ListParser::JsonPath(
+                parse_json_path(list_json_path)
+                    .map_err(|e| ScriptConfigError::InvalidJsonPath(config.name.clone(), e))?,
+            ),
+            (None, None) => return Err(ScriptConfigError::MissingListParser(config.name)),
+            (Some(_), Some(_)) => return Err(ScriptConfigError::SeveralListParsers(config.name)),
+        };
+
+        Ok(Self {
+            config,
+            list_parser,
+            placeholder_regex: Regex::new(r"\{(\w+)\}").unwrap(),
+            control: RunControl::default(),
+        })
+    }
+
+    fn expand_template(&self, template: &str, package: &PackageData) -> String {
+        self.placeholder_regex
+            .replace_all(template, |captures: &regex::Captures| {
+                let key = &captures[1];
+                let value = if key == PACKAGE_PLACEHOLDER {
+                    package.package_name.as_str()
+                } else {
+                    package.metadata.get(key).map(String::as_str).unwrap_or("")
+                };
+
+                shell_quote(value)
+            })
+            .to_string()
+    }
+
+    fn run(
+        &self,
+        template: &str,
+        package: &PackageData,
+        action: &str,
+        to_error: fn(String) -> ToolError,
+    ) -> ToolResult<()> {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(self.expand_template(template, package));
+
+        self.control.run(
+            &mut command,
+            &self.config.name,
+            &package.package_name,
+            action,
+            to_error,
+        )
+    }
+
+    fn parse_list(&self, output: &str) -> ToolResult<Vec<RawPackageData>> {
+        let packages: Vec<(String, PackageMetadata)> = match &self.list_parser {
+            ListParser::Regex(regex) => output
+                .lines()
+                .filter_map(|line| {
+                    let captures = regex.captures(line)?;
+                    let package_name = captures.name(PACKAGE_PLACEHOLDER)?.as_str().to_string();
+                    let metadata = regex
+                        .capture_names()
+                        .flatten()
+                        .filter(|name| *name != PACKAGE_PLACEHOLDER)
+                        .filter_map(|name| {
+                            Some((name.to_string(), captures.name(name)?.as_str().to_string()))
+                        })
+                        .collect();
+
+                    Some((package_name, metadata))
+                })
+                .collect(),
+            ListParser::JsonPath(segments) => {
+                let root = serde_json::from_str::<serde_json::Value>(output).map_err(|e| {
+                    ToolError::ListingPackages(format!(
+                        "Invalid output for script installer {}: {e}",
+                        self.config.name
+                    ))
+                })?;
+
+                select_json_path(root, segments)
+                    .into_iter()
+                    .filter_map(|value| Some((value.as_str()?.to_string(), PackageMetadata::new())))
+                    .collect()
+            }
+        };
+
+        Ok(packages
+            .into_iter()
+            .filter(|(package_name, _)| !package_name.is_empty())
+            .map(|(package_name, metadata)| RawPackageData {
+                package_name,
+                category_name: String::new(),
+                installation_tool: self.get_type(),
+                metadata,
+            })
+            .collect())
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn parse_json_path(path: &str) -> Result<Vec<JsonPathSegment>, String> {
+    let path = path.strip_prefix('$').unwrap_or(path);
+
+    path.replace("[*]", ".*")
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .map(|segment| match segment {
+            "*" => Ok(JsonPathSegment::Values),
+            "*~" => Ok(JsonPathSegment::Keys),
+            key if key.contains(['*', '[', ']']) => Err(format!("unsupported segment {key}")),
+            key => Ok(JsonPathSegment::Key(key.to_string())),
+        })
+        .collect()
+}
+
+fn select_json_path(
+    root: serde_json::Value,
+    segments: &[JsonPathSegment],
+) -> Vec<serde_json::Value> {
+    use serde_json::Value;
+
+    segments.iter().fold(vec![root], |values, segment| {
+        values
+            .into_iter()
+            .flat_map(|value| match (segment, value) {
+                (JsonPathSegment::Key(key), Value::Object(mut object)) => {
+                    object.remove(key).into_iter().collect()
+                }
+                (JsonPathSegment::Values, Value::Array(array)) => array,
+                (JsonPathSegment::Values, Value::Object(object)) => {
+                    object.into_iter().map(|(_, value)| value).collect()
+                }
+                (JsonPathSegment::Keys, Value::Object(object)) => object
+                    .into_iter()
+                    .map(|(key, _)| Value::String(key))
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect()
+    })
+}
+
+impl InstallationTool for ScriptInstaller {
+    fn get_type(&self) -> InstallationTools {
+        InstallationTools::Script(self.config.name.clone())
+    }
+
+    fn set_run_control(&mut self, control: RunControl) {
+        self.control = control;
+    }
+
+    fn install_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.run(
+            &self.config.install,
+            package,
+            "install",
+            ToolError::InstallingPackage,
+        )
+    }
+
+    fn delete_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.run(
+            &self.config.remove,
+            package,
+            "remove",
+            ToolError::DeletingPackage,
+        )
+    }
+
+    fn update_package(&mut self, package: &PackageData) -> ToolResult<()> {
+        self.run(
+            &self.config.update,
+            package,
+            "update",
+            ToolError::UpdatingPackage,
+        )
+    }
+
+    fn get_packages(&self) -> ToolResult<Vec<RawPackageData>> {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg(&self.config.list);
+
+        self.parse_list(&listing_output(command, &self.control)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pazckager_core::testing::test_package;
+    use std::fs;
+
+    fn script_config(config: &str) -> ScriptConfig {
+        toml::from_str(config).unwrap()
+    }
+
+    #[test]
+    fn test_commands_are_expanded() -> ToolResult<()> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let calls_path = temp_dir.path().join("calls");
+        let calls = calls_path.to_str().unwrap();
+        let mut installer = ScriptInstaller::new(script_config(&format!(
+            r#"
+            name = "go"
+            install = "echo install {{package}}@{{version}} >> {calls}"
+            remove = "echo remove {{package}} >> {calls}"
+            update = "echo update {{package}}{{missing}} >> {calls}"
+            list = "true"
+            list_regex = '^(?P<package>\S+)$'
+            "#
+        )))
+        .unwrap();
+
+        let package = test_package(
+            InstallationTools::Script("go".to_string()),
+            "golang.org/x/tools/gopls",
+            &[("version", "v0.15.3")],
+        );
+        installer.install_package(&package)?;
+        installer.update_package(&package)?;
+        installer.delete_package(&test_package(
+            InstallationTools::Script("go".to_string()),
+            "it's; rm -rf",
+            &[],
+        ))?;
+
+        assert_eq!(installer.get_type().to_string(), "script:go");
+        assert_eq!(
+            fs::read_to_string(&calls_path).unwrap(),
+            "install golang.org/x/tools/gopls@v0.15.3\n\
+             update golang.org/x/tools/gopls\n\
+             remove it's; rm -rf\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_command() {
+        let mut installer = ScriptInstaller::new(script_config(
+            r#"
+            name = "failing"
+            install = "exit 3"
+            remove = "exit 3"
+            update = "exit 3"
+            list = "exit 3"
+            list_regex = '^(?P<package>\S+)$'
+            "#,
+        ))
+        .unwrap();
+
+        let result = installer.install_package(&test_package(
+            InstallationTools::Script("failing".to_string()),
+            "gopls",
+            &[],
+        ));
+
+        assert!(matches!(result, Err(ToolError::InstallingPackage(_))));
+        assert!(matches!(
+            installer.get_packages(),
+            Err(ToolError::ListingPackages(_))
+        ));
+    }
+
+    #[test]
+    fn test_list_regex() {
+        let installer = ScriptInstaller::new(script_config(
+            r#"
+            name = "gem"
+            install = "gem install {package}"
+            remove = "gem uninstall {package}"
+            update = "gem update {package}"
+            list = "printf 'rake (13.2.1)\nrubocop (1.64.1)\n\n'"
+            list_regex = '^(?P<package>\S+) \((?P<version>[^)]+)\)$'
+            "#,
+        ))
+        .unwrap();
+
+        let packages = installer
+            .get_packages()
+            .unwrap()
+            .into_iter()
+            .map(|package| (package.package_name, package.metadata))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            packages,
+            vec![
+                (
+                    "rake".to_string(),
+                    test_package(
+                        InstallationTools::Script("gem".to_string()),
+                        "rake",
+                        &[("version", "13.2.1")]
+                    )
+                    .metadata
+                ),
+                (
+                    "rubocop".to_string(),
+                    test_package(
+                        InstallationTools::Script("gem".to_string()),
+                        "rubocop",
+                        &[("version", "1.64.1")]
+                    )
+                    .metadata
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_list_json_path() {
+        let installer = |list_json_path: &str| {
+            ScriptInstaller::new(ScriptConfig {
+                name: "json".to_string(),
+                install: String::new(),
+                remove: String::new(),
+                update: String::new(),
+                list: String::new(),
+                list_regex: None,
+                list_json_path: Some(list_json_path.to_string()),
+            })
+            .unwrap()
+        };
+        let output = r#"{
+            "dependencies": { "typescript": {}, "eslint": {} },
+            "items": [{ "name": "appimagetool" }, { "name": "obsidian" }, { "id": 3 }]
+        }"#;
+
+        let package_names = |list_json_path: &str| {
+            installer(list_json_path)
+                .parse_list(output)
+                .unwrap()
+                .into_iter()
+                .map(|package| package.package_name)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            package_names("$.dependencies.*~"),
+            vec!["eslint", "typescript"]
+        );
+        assert_eq!(
+            package_names("$.items[*].name"),
+            vec!["appimagetool", "obsidian"]
+        );
+        assert!(package_names("$.missing.*").is_empty());
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        let config = |list_regex: Option<&str>, list_json_path: Option<&str>| ScriptConfig {
+            name: "invalid".to_string(),
+            install: String::new(),
+            remove: String::new(),
+            update: String::new(),
+            list: String::new(),
+            list_regex: list_regex.map(str::to_string),
+            list_json_path: list_json_path.map(str::to_string),
+        };
+
+        assert!(matches!(
+            ScriptInstaller::new(config(None, None)),
+            Err(ScriptConfigError::MissingListParser(_))
+        ));
+        assert!(matches!(
+            ScriptInstaller::new(config(Some("(?P<package>.*)"), Some("$.*"))),
+            Err(ScriptConfigError::SeveralListParsers(_))
+        ));
+        assert!(matches!(
+            ScriptInstaller::new(config(Some("(.*)"), None)),
+            Err(ScriptConfigError::MissingPackageGroup(_))
+        ));
+        assert!(matches!(
+            ScriptInstaller::new(config(Some("(?P<package>"), None)),
+            Err(ScriptConfigError::InvalidListRegex(_, _))
+        ));
+        assert!(matches!(
+            ScriptInstaller::new(config(None, Some("$.items[0]"))),
+            Err(ScriptConfigError::InvalidJsonPath(_, _))
+        ));
+    }
+}